@@ -10,8 +10,8 @@ use std::collections::BTreeMap;
 use handlebars::{Handlebars, Context, Template};
 use rustc_serialize::json::{Json, ToJson};
 
-static UNITS_DIR: &'static str = "units";
-static MAN_DIR: &'static str = "man";
+static UNITS_DIR: &str = "units";
+static MAN_DIR: &str = "man";
 
 fn main() {
     let out_dir = env::var("OUT_DIR").unwrap();
@@ -24,17 +24,20 @@ fn main() {
     let data = build_render_data();
 
     let mut config = File::create(out_dir.clone() + "/config.rs").unwrap();
-    writeln!(config, "pub static USERS_CRONTAB_DIR: &'static str = {:?};", data["statedir"].as_string().unwrap()).unwrap();
-    writeln!(config, "pub static PACKAGE: &'static str = {:?};", data["package"].as_string().unwrap()).unwrap();
-    writeln!(config, "pub static BIN_DIR: &'static str = {:?};", data["bindir"].as_string().unwrap()).unwrap();
-    writeln!(config, "pub static LIB_DIR: &'static str = {:?};", data["libdir"].as_string().unwrap()).unwrap();
+    writeln!(config, "pub static USERS_CRONTAB_DIR: &str = {:?};", data["statedir"].as_string().unwrap()).unwrap();
+    writeln!(config, "pub static PACKAGE: &str = {:?};", data["package"].as_string().unwrap()).unwrap();
+    writeln!(config, "pub static BIN_DIR: &str = {:?};", data["bindir"].as_string().unwrap()).unwrap();
+    writeln!(config, "pub static LIB_DIR: &str = {:?};", data["libdir"].as_string().unwrap()).unwrap();
 
     let mut data = Json::Object(data);
     let schedules = get_required_schedules();
 
     for schedule in schedules.iter() {
+        // run-parts directories share a single template service, only boot has its own
+        let service = if schedule == "boot" { "cron-boot.service".to_owned() } else { format!("cron-run-parts@{}.service", schedule) };
         data.as_object_mut().unwrap().insert("schedule".to_owned(), Json::String(schedule.clone()));
-        for schedule_unit in [ "target", "timer", "service" ].iter() {
+        data.as_object_mut().unwrap().insert("service".to_owned(), Json::String(service));
+        for schedule_unit in [ "target", "timer" ].iter() {
             compile_template(
                 format!("{}/cron-schedule.{}.in", UNITS_DIR, schedule_unit),
                 output.join("units").join(format!("cron-{}.{}", schedule, schedule_unit)),
@@ -1,6 +1,6 @@
 use std::fs::File;
 use std::io::Read;
-use std::mem::transmute;
+use std::str;
 use std::thread::sleep;
 use std::env;
 use std::time::Duration;
@@ -21,7 +21,7 @@ fn main() {
         .map(|sz| {
             buf.iter()
                .position(|&c| c == 0x20)
-               .and_then(|p| if p < sz { unsafe { transmute::<_, &str>(&buf[..p]) }.parse::<f32>().ok() } else { None })
+               .and_then(|p| if p < sz { str::from_utf8(&buf[..p]).ok().and_then(|s| s.parse::<f32>().ok()) } else { None })
                .unwrap()
         })
         .unwrap();
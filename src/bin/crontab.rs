@@ -1,4 +1,5 @@
-extern crate rustc_serialize;
+#[macro_use]
+extern crate serde;
 extern crate docopt;
 extern crate users;
 extern crate glob;
@@ -34,7 +35,7 @@ fn change_owner<P: AsRef<Path>>(path: P, owner: libc::uid_t, group: libc::gid_t)
     }
 }
 
-static USAGE: &'static str = r#"
+static USAGE: &str = r#"
 Usage: crontab [-u <user>] -l
        crontab [-u <user>] -e [<file>]
        crontab [-u <user>] -s
@@ -68,7 +69,7 @@ Options:
                             crontab.
 "#;
 
-#[derive(Debug, RustcDecodable)]
+#[derive(Debug, Deserialize)]
 #[allow(non_snake_case)]
 struct Args {
     arg_file: Option<String>,
@@ -128,7 +129,7 @@ fn list(cron_file: &Path, cron_user: &User, _args: &Args) -> i32 {
 fn remove(cron_file: &Path, cron_user: &User, args: &Args) -> i32 {
     let mut stderr = stderr();
 
-    if !args.flag_ask || confirm(&*format!("Are you sure you want to delete {} (y/n)? ", cron_file.display())) {
+    if !args.flag_ask || confirm(&format!("Are you sure you want to delete {} (y/n)? ", cron_file.display())) {
         if let Err(e) = fs::remove_file(cron_file) {
             use std::io::ErrorKind::*;
             match e.kind() {
@@ -146,14 +147,12 @@ fn show(_cron_file: &Path, _cron_user: &User, _args: &Args) -> i32 {
     let mut stderr = stderr();
 
     if let Ok(dir) = fs::read_dir(USERS_CRONTAB_DIR) {
-        for item in dir {
-            if let Ok(entry) = item {
-                if let Some(name) = entry.file_name().to_str() {
-                    if users::get_user_by_name(name).is_some() {
-                        println!("{}", name);
-                    } else {
-                        writeln!(stderr, "WARNING: crontab found with no matching user: {}", name).unwrap();
-                    }
+        for entry in dir.flatten() {
+            if let Some(name) = entry.file_name().to_str() {
+                if users::get_user_by_name(name).is_some() {
+                    println!("{}", name);
+                } else {
+                    writeln!(stderr, "WARNING: crontab found with no matching user: {}", name).unwrap();
                 }
             }
         }
@@ -258,7 +257,7 @@ fn replace(cron_file: &Path, cron_user: &User, args: &Args) -> i32 {
 fn main() {
     let mut stderr = stderr();
     let args: Args = Docopt::new(USAGE)
-        .and_then(|d| d.decode())
+        .and_then(|d| d.deserialize())
         .unwrap_or_else(|e| e.exit());
 
     let cron_user = match args.flag_user {
@@ -267,7 +266,7 @@ fn main() {
             exit(1);
         }
         Some(ref user) => {
-            match users::get_user_by_name(&**user) {
+            match users::get_user_by_name(user) {
                 Some(user) => user,
                 None => {
                     writeln!(stderr, "unknown user: {}", user).unwrap();
@@ -283,11 +282,9 @@ fn main() {
             writeln!(stderr, "{} is not a directory!", USERS_CRONTAB_DIR).unwrap();
             exit(1);
         }
-        Err(_) => {
-            if let Err(_) = fs::create_dir_all(USERS_CRONTAB_DIR) {
-                writeln!(stderr, "{} doesn't exist!", USERS_CRONTAB_DIR).unwrap();
-                exit(1);
-            }
+        Err(_) if fs::create_dir_all(USERS_CRONTAB_DIR).is_err() => {
+            writeln!(stderr, "{} doesn't exist!", USERS_CRONTAB_DIR).unwrap();
+            exit(1);
         }
         _ => (),
     }
@@ -295,17 +292,17 @@ fn main() {
     let cron_file = PathBuf::from(USERS_CRONTAB_DIR).join(cron_user.name());
 
     exit(match args {
-        Args { flag_show: true, .. } => show(&*cron_file, &cron_user, &args),
-        Args { flag_list: true, .. } => list(&*cron_file, &cron_user, &args),
-        Args { flag_edit: true, arg_file: None, .. } => edit(&*cron_file, &cron_user, &args),
-        Args { flag_edit: true, .. } => replace(&*cron_file, &cron_user, &args),
-        Args { flag_remove: true, .. } => remove(&*cron_file, &cron_user, &args),
+        Args { flag_show: true, .. } => show(&cron_file, &cron_user, &args),
+        Args { flag_list: true, .. } => list(&cron_file, &cron_user, &args),
+        Args { flag_edit: true, arg_file: None, .. } => edit(&cron_file, &cron_user, &args),
+        Args { flag_edit: true, .. } => replace(&cron_file, &cron_user, &args),
+        Args { flag_remove: true, .. } => remove(&cron_file, &cron_user, &args),
         _ => unreachable!(),
     })
 }
 
 fn check_crontab_syntax<P: AsRef<Path>>(path: P) -> Result<(), CrontabFileError> {
-    match try!(CrontabFile::<UserCrontabEntry>::new(path)).find(Result::is_err) {
+    match CrontabFile::<UserCrontabEntry>::new(path)?.find(Result::is_err) {
         Some(Err(err)) => Err(err),
         _ => Ok(()),
     }
@@ -1,7 +1,7 @@
 
 use std::env;
 use std::process::{Command, Stdio};
-use std::io::{Result, Write};
+use std::io::{ErrorKind, Result, Write};
 
 macro_rules! try_log {
     ($exp:expr) => {
@@ -20,12 +20,38 @@ fn get_systemd_unit_property(unit: &str, prop: &str) -> Result<String> {
         .arg(prop)
         .output()
         .map(|out| {
-            String::from_utf8_lossy(&out.stdout[prop.len() + 1..])
-                .trim_end_matches('\n')
-                .to_owned()
+            let value = String::from_utf8_lossy(&out.stdout).trim_end_matches('\n').to_owned();
+            value.strip_prefix(&*format!("{}=", prop)).map(str::to_owned).unwrap_or(value)
         })
 }
 
+/// Splits a space separated list the way systemctl quotes it, e.g. the variables
+/// of `Environment=`, where `"NAME=a value"` is one variable, and the way the
+/// shell would, e.g. a command and its arguments: double or single quotes keep
+/// the spaces between them, and a backslash escapes the next character.
+fn split_quoted(list: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = None;
+    let mut quote = None;
+    let mut chars = list.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('\''), c) => word.get_or_insert_with(String::new).push(c),
+            (_, '\\') => word.get_or_insert_with(String::new).extend(chars.next()),
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '"') | (None, '\'') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
 fn main() {
     let unit = match env::args().nth(1) {
         Some(unit) => unit,
@@ -35,24 +61,26 @@ fn main() {
         }
     };
 
-    let mut user = try_log!(get_systemd_unit_property(&*unit, "User"));
-    if user.len() == 0 {
+    let mut user = try_log!(get_systemd_unit_property(&unit, "User"));
+    if user.is_empty() {
         user = "root".to_owned();
     }
 
 
-    let job_env = try_log!(get_systemd_unit_property(&*unit, "Environment"));
-    for pair in job_env.split(' ') {
+    let mut mail_command = None;
+    let job_env = try_log!(get_systemd_unit_property(&unit, "Environment"));
+    for pair in split_quoted(&job_env) {
         let mut p = pair.splitn(2, '=');
-        if let (Some(name), Some(value)) = (p.next(), p.next()) {
-            if name == "MAILTO" {
-                user = value.to_owned();
-                break;
-            }
+        match (p.next(), p.next()) {
+            (Some("MAILTO"), Some(value)) => user = value.to_owned(),
+            (Some("MAIL_COMMAND"), Some(value)) if !value.trim().is_empty() => mail_command = Some(split_quoted(value)),
+            _ => (),
         }
     }
 
-    if user.len() == 0 {
+    // MAILTO may list several recipients separated by commas
+    let recipients = user.split(',').map(str::trim).filter(|r| !r.is_empty()).collect::<Vec<_>>();
+    if recipients.is_empty() {
         return;
     }
 
@@ -63,18 +91,18 @@ fn main() {
         .trim_end_matches('\n')
         .to_owned();
 
-    if hostname.len() == 0 {
+    if hostname.is_empty() {
         hostname = "localhost".to_owned();
     }
 
+    let subject = format!("[{}] job {} failed", hostname, unit);
+
     let mut head = String::new();
     head.push_str("From: root (systemd-cron)\nTo: ");
-    head.push_str(&*user);
-    head.push_str("\nSubject: [");
-    head.push_str(&*hostname);
-    head.push_str("] job ");
-    head.push_str(&*unit);
-    head.push_str(r###" failed
+    head.push_str(&recipients.join(", "));
+    head.push_str("\nSubject: ");
+    head.push_str(&subject);
+    head.push_str(r###"
 MIME-Version: 1.0
 Content-Type: text/plain; charset=UTF-8
 Content-Transfer-Encoding: 8bit
@@ -88,16 +116,52 @@ Auto-Submitted: auto-generated
         .output()
         .unwrap();
 
-    let mut mailer = try_log!(Command::new("sendmail")
-        .arg("-i")
-        .arg("-B8BITMIME")
-        .arg(&*user)
-        .stdin(Stdio::piped())
-        .spawn());
+    // mail(1) and its kin take the subject as an option and write the headers
+    // themselves, anything else is expected to work like sendmail
+    let mail_command = mail_command.unwrap_or_else(|| vec!["/usr/bin/mail".to_owned()]);
+    let command = &mail_command[0];
+    let mut mailer = Command::new(command);
+    mailer.args(&mail_command[1..]);
+    let mailx = matches!(command.rsplit('/').next(), Some("mail") | Some("mailx") | Some("s-nail"));
+    if mailx {
+        mailer.arg("-s").arg(&subject);
+        head.clear();
+    } else {
+        mailer.arg("-i");
+        if command.rsplit('/').next() == Some("sendmail") {
+            mailer.arg("-B8BITMIME");
+        }
+    }
+
+    let mut mailer = match mailer.args(&recipients).stdin(Stdio::piped()).spawn() {
+        Ok(mailer) => mailer,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => {
+            println!("<5>can't send mail for {}, {} isn't installed", unit, command);
+            return;
+        }
+        Err(e) => {
+            println!("<3>{}", e);
+            return;
+        }
+    };
 
     if let Some(ref mut stdin) = mailer.stdin {
-        try_log!(stdin.write_all(head.as_bytes()).and_then(|_| stdin.write_all(&*status.stdout)));
+        try_log!(stdin.write_all(head.as_bytes()).and_then(|_| stdin.write_all(&status.stdout)));
     }
 
     mailer.wait().unwrap();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::split_quoted;
+
+    #[test]
+    fn quoted_words_are_kept_together() {
+        assert_eq!(split_quoted(r#"MAILTO=root "MAIL_COMMAND=/usr/bin/msmtp -a \"my account\"" 'A=b c'"#),
+                   vec!["MAILTO=root", r#"MAIL_COMMAND=/usr/bin/msmtp -a "my account""#, "A=b c"]);
+        assert_eq!(split_quoted(r#"/usr/bin/msmtp -a "my account""#), vec!["/usr/bin/msmtp", "-a", "my account"]);
+        assert_eq!(split_quoted("  a\\ b  '' c "), vec!["a b", "", "c"]);
+        assert!(split_quoted("").is_empty());
+    }
+}
@@ -9,21 +9,21 @@ use std::collections::BTreeSet;
 use time::{Duration, get_time};
 use glob::glob;
 
-static KNOWN_STAMPS: [&'static str; 6] = ["/var/lib/systemd/timers/stamp-cron-daily.timer",
-                                          "/var/lib/systemd/timers/stamp-cron-weekly.timer",
-                                          "/var/lib/systemd/timers/stamp-cron-monthly.timer",
-                                          "/var/lib/systemd/timers/stamp-cron-quarterly.timer",
-                                          "/var/lib/systemd/timers/stamp-cron-semi-annually.timer",
-                                          "/var/lib/systemd/timers/stamp-cron-yearly.timer"];
+static KNOWN_STAMPS: [&str; 6] = ["/var/lib/systemd/timers/stamp-cron-daily.timer",
+                                  "/var/lib/systemd/timers/stamp-cron-weekly.timer",
+                                  "/var/lib/systemd/timers/stamp-cron-monthly.timer",
+                                  "/var/lib/systemd/timers/stamp-cron-quarterly.timer",
+                                  "/var/lib/systemd/timers/stamp-cron-semi-annually.timer",
+                                  "/var/lib/systemd/timers/stamp-cron-yearly.timer"];
 
-static ACTUAL_STAMPS_GLOB: &'static str = "/var/lib/systemd/timers/stamp-cron-*.timer";
-static TIMER_STAMPS_GLOB: &'static str = "/run/systemd/generator/cron-*.timer";
+static ACTUAL_STAMPS_GLOB: &str = "/var/lib/systemd/timers/stamp-cron-*.timer";
+static TIMER_STAMPS_GLOB: &str = "/run/systemd/generator/cron-*.timer";
 
 fn cleanup<P: AsRef<Path>, I: IntoIterator<Item = P>>(iter: I) {
     let ten_days_ago = get_time() - Duration::days(10);
     for stamp in iter {
         if let Ok(meta) = metadata(&stamp) {
-            if (meta.mtime() as i64) < ten_days_ago.sec {
+            if meta.mtime() < ten_days_ago.sec {
                 let _ = remove_file(&stamp);
             }
         }
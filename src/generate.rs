@@ -1,327 +1,3217 @@
-use std::io::{self, Write};
-use std::fs::{File, create_dir_all, metadata, set_permissions};
+use std::io::{self, Read, Write};
+use std::fs::{File, create_dir_all, metadata, remove_file, set_permissions};
 use std::os::unix::ffi::OsStrExt;
 use std::os::unix::fs::{MetadataExt, PermissionsExt, symlink};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::collections::{BTreeMap, BTreeSet};
 
 use cronparse::Limited;
-use cronparse::crontab::{CrontabEntry, SystemCrontabEntry, UserCrontabEntry};
-use cronparse::schedule::{Calendar, Period, Schedule};
+use cronparse::crontab::{AnacrontabEntry, CrontabEntry, SystemCrontabEntry, UserCrontabEntry};
+use cronparse::schedule::{Calendar, Day, DayOfWeek, Period, Schedule};
 use cronparse::interval::Interval;
 
-use pgs_files::passwd::{get_entry_by_name, get_entry_by_uid};
+use rustc_serialize::base64::{STANDARD, ToBase64};
 
-use super::{LIB_DIR, PACKAGE, REBOOT_FILE};
 
-pub fn generate_systemd_units(entry: CrontabEntry, env: &BTreeMap<String, String>, path: &Path, dstdir: &Path) -> io::Result<()> {
+use process::GeneratorConfig;
+use super::{LIB_DIR, PACKAGE, VERSION};
+
+static MAX_UNIT_LINE_LEN: usize = 1024;
+/// Anacrontab periods of this many days or more run yearly, as a stride of months
+/// can't go past a year.
+static YEARLY_DAYS: u16 = 360;
+static STRICT_HARDENING: [&str; 10] = ["NoNewPrivileges=true", "PrivateTmp=true", "ProtectKernelTunables=true",
+                                       "ProtectKernelModules=true", "ProtectControlGroups=true",
+                                       "RestrictAddressFamilies=AF_UNIX AF_INET AF_INET6", "RestrictRealtime=true",
+                                       "RestrictSUIDSGID=true", "LockPersonality=true", "MemoryDenyWriteExecute=true"];
+/// Crontab variables that control how the jobs are translated, rather than
+/// being part of their environment like PATH or MAILTO.
+pub static CONTROL_VARS: [&str; 52] = ["AFTER", "AFTER_TARGET", "ALLOWED_ENV", "ASSERT_PATH",
+                                       "BATCH", "COALESCE", "CONFLICTS", "CPU_QUOTA", "CRON_TZ", "DELAY",
+                                       "DESCRIPTION", "DISABLED", "DROP_IN", "EARLY", "ENV_FILE",
+                                       "FIRST_BOOT_ONLY", "FIXED_RANDOM_DELAY", "HARDENING", "INTERVAL",
+                                       "JOB_TIMEOUT", "KEEP_ON_ISOLATE", "LAST_WEEKDAY", "LOCK",
+                                       "MEMORY_HIGH", "MEMORY_MAX", "MINUTELY_STEP", "NAME", "ONCALENDAR",
+                                       "OUTPUT", "PERSISTENT", "PROTECT_HOME", "RANDOM_DELAY",
+                                       "READ_WRITE_PATHS", "REQUIRES", "REQUIRE_MOUNT", "REQUIRE_PATH",
+                                       "RESTART", "RESTART_SEC", "RUNTIME_MAX", "RUN_AFTER", "SERVICE_TYPE",
+                                       "SHELL_ARGS", "SLICE", "START_HOURS_RANGE", "SUCCESS_EXIT",
+                                       "TOUCH_ON_SUCCESS", "TRIGGERS", "UMASK", "WATCHDOG", "WEEKLY_DAY",
+                                       "WEEK_START", "YEAR"];
+static SYSTEM_UNIT_DIRS: [&str; 4] = ["/etc/systemd/system", "/run/systemd/system", "/usr/lib/systemd/system", "/lib/systemd/system"];
+
+/// What became of a single crontab entry.
+pub enum Outcome {
+    /// Units were generated (or would have been, in a dry run), with the
+    /// `OnCalendar=` expressions of the timer, if it isn't a boot timer, the
+    /// files written, relative to the output directory, and in a dry run, the
+    /// names and contents of the units and script that would have been.
+    Generated(Vec<String>, Vec<PathBuf>, Vec<(String, String)>),
+    /// The entry was understood but deliberately not turned into units.
+    Skipped(String),
+}
+
+/// Where a crontab entry was read from.
+pub struct EntrySource<'a> {
+    pub path: &'a Path,
+    pub lineno: usize,
+    /// The line as it was written.
+    pub line: &'a str,
+    /// The line is a `@shutdown` one, parsed as `@reboot` as cron has no such period.
+    pub at_shutdown: bool,
+    /// The comment right above the line, which describes the job better than
+    /// `DESCRIPTION` or the line itself.
+    pub comment: Option<&'a str>,
+}
+
+/// A job named in the `REQUIRES`, `AFTER` or `CONFLICTS` of another. The names
+/// are only looked up once all crontabs are read, so a job can name one further
+/// down or in another crontab, and two jobs can conflict with each other.
+#[derive(Debug, Clone)]
+pub struct Dependency {
+    pub path: PathBuf,
+    pub lineno: usize,
+    /// Directory the service of the job went into.
+    pub dstdir: PathBuf,
+    pub unit: String,
+    pub var: &'static str,
+    pub name: String,
+}
+
+impl Dependency {
+    /// The directives the variable stands for: the jobs a job requires are started
+    /// before it, the ones it conflicts with are stopped when it starts, and the
+    /// other way round.
+    pub fn directives(&self) -> &'static [&'static str] {
+        match self.var {
+            "REQUIRES" => &["Requires", "After"],
+            "AFTER" => &["After"],
+            _ => &["Conflicts"],
+        }
+    }
+}
+
+/// Writes the `dependencies.conf` drop-in of a service, with a `[Unit]` section
+/// made of `directives`, returning its path relative to `dstdir` and its contents.
+/// In a dry run nothing is written.
+pub fn write_dependencies(dstdir: &Path, unit_name: &str, directives: &[String], dry_run: bool)
+                          -> io::Result<(PathBuf, String)> {
+    let section = format!("[Unit]\n{}\n", directives.join("\n"));
+    let drop_in = Path::new(&format!("{}.d", unit_name)).join("dependencies.conf");
+    if !dry_run {
+        write_drop_in(dstdir, unit_name, "dependencies.conf", &[section.as_bytes()])?;
+    }
+    Ok((drop_in, render_unit(&[section.as_bytes()])))
+}
+
+/// Translates a crontab entry into a service and timer pair in the configured directory.
+/// In a dry run the whole translation runs, but nothing is written, the units are
+/// returned instead. Problems that don't stop the translation, like an ignored
+/// variable, are added to `warnings`.
+pub fn generate_systemd_units(entry: CrontabEntry, env: &BTreeMap<String, String>, source: &EntrySource,
+                              config: &GeneratorConfig, warnings: &mut Vec<String>)
+                              -> io::Result<Outcome> {
     use cronparse::crontab::CrontabEntry::*;
 
+    let EntrySource { path, lineno, line, .. } = *source;
+
     info!("generating units for {}: \"{}\", {:?}", path.display(), entry, env);
 
-    let owner = try!(metadata(path)).uid();
+    let owner = match config.owner {
+        Some(owner) => owner,
+        None => metadata(path)?.uid(),
+    };
 
     let mut persistent = env.get("PERSISTENT")
                             .and_then(|v| {
-                                match &**v {
+                                match &*v.trim().to_lowercase() {
                                     "yes" | "true" | "1" => Some(true),
                                     "auto" | "" => None,
                                     _ => Some(false),
                                 }
                             })
-                            .unwrap_or_else(|| {
-                                match entry {
-                                    Anacron(_) |
-                                    User(UserCrontabEntry { sched: Schedule::Period(_), .. }) |
-                                    System(SystemCrontabEntry { sched: Schedule::Period(_), .. }) => true,
-                                    _ => false,
-                                }
-                            });
-
-    let batch = env.get("BATCH")
-                   .map(|v| {
-                       match &**v {
-                           "yes" | "true" | "1" => true,
-                           _ => false,
-                       }
-                   })
-                   .unwrap_or(false);
+                            .or(config.default_persistent)
+                            .unwrap_or(matches!(entry,
+                                                Anacron(_) |
+                                                User(UserCrontabEntry { sched: Schedule::Period(_), .. }) |
+                                                System(SystemCrontabEntry { sched: Schedule::Period(_), .. })));
+
+    let batch = env_flag(env, "BATCH");
 
-    let random_delay = env.get("RANDOM_DELAY").and_then(|v| v.parse::<u64>().ok()).unwrap_or(1);
     let mut delay = env.get("DELAY").and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
-    let hour = env.get("START_HOURS_RANGE")
-                  .and_then(|v| v.splitn(1, '-').next().and_then(|v| v.parse::<u64>().ok()))
-                  .unwrap_or(0);
+    let (hour, end_hour) = match env.get("START_HOURS_RANGE").map(|v| v.trim()) {
+        None => (0, None),
+        Some(range) => {
+            // periods of a day or more start at the start of the range, hourly jobs run within it
+            let mut bounds = range.splitn(2, '-').map(|v| v.trim().parse::<u64>().ok());
+            match (bounds.next(), bounds.next()) {
+                (Some(Some(hour)), end) if hour <= 23 => (hour, end.and_then(|end| end.filter(|&end| end <= 23))),
+                _ => {
+                    warnings.push(format!("ignoring invalid START_HOURS_RANGE, expected a start hour from 0 to 23: \"{}\"", range));
+                    (0, None)
+                }
+            }
+        }
+    };
     let shell = env.get("SHELL").map(|v| &**v).unwrap_or("/bin/sh");
-    let daemon_reload = metadata(REBOOT_FILE).map(|m| m.is_file()).unwrap_or(false);
-
-    let schedule = entry.period()
-                        .and_then(|period| {
-                            match *period {
-                                Period::Reboot => {
-                                    persistent = false;
-                                    if delay == 0 {
-                                        delay = 1;
-                                    }
-                                    None
-                                }
-                                Period::Minutely => {
-                                    persistent = false;
-                                    Some("minutely".to_owned())
-                                }
-                                Period::Hourly => if delay == 0 { Some("hourly".to_owned()) } else { Some(format!("*-*-* *:{}:0", delay)) },
-                                Period::Midnight => {
-                                    if delay == 0 { Some("daily".to_owned()) } else { Some(format!("*-*-* 0:{}:0", delay)) }
-                                }
-                                Period::Daily => {
-                                    if delay == 0 && hour == 0 {
-                                        Some("daily".to_owned())
-                                    } else {
-                                        Some(format!("*-*-* {}:{}:0", hour, delay))
-                                    }
-                                }
-                                Period::Weekly => {
-                                    if delay == 0 && hour == 0 {
-                                        Some("weekly".to_owned())
-                                    } else {
-                                        Some(format!("Mon *-*-* {}:{}:0", hour, delay))
-                                    }
-                                }
-                                Period::Monthly => {
-                                    if delay == 0 && hour == 0 {
-                                        Some("monthly".to_owned())
-                                    } else {
-                                        Some(format!("*-*-1 {}:{}:0", hour, delay))
-                                    }
-                                }
-                                Period::Quaterly => {
-                                    if delay == 0 && hour == 0 {
-                                        Some("quaterly".to_owned())
-                                    } else {
-                                        Some(format!("*-1,4,7,10-1 {}:{}:0", hour, delay))
-                                    }
-                                }
-                                Period::Biannually => {
-                                    if delay == 0 && hour == 0 {
-                                        Some("semiannually".to_owned())
-                                    } else {
-                                        Some(format!("*-1,7-1 {}:{}:0", hour, delay))
-                                    }
-                                }
-                                Period::Yearly => {
-                                    if delay == 0 && hour == 0 {
-                                        Some("yearly".to_owned())
-                                    } else {
-                                        Some(format!("*-1-1 {}:{}:0", hour, delay))
-                                    }
-                                }
-                                Period::Days(days) => {
-                                    // workaround for anacrontab
-                                    if days > 31 {
-                                        Some(format!("*-1/{}-1 {}:{}:0", days / 30, hour, delay))
-                                    } else {
-                                        Some(format!("*-*-1/{} {}:{}:0", days, hour, delay))
-                                    }
-                                }
-                            }
-                        })
-                        .or_else(|| {
-                            entry.calendar().and_then(|cal| {
-                                let Calendar { ref dows, ref days, ref mons, ref hrs, ref mins } = *cal;
-
-                                Some(format!("{} *-{}-{} {}:{}:00",
-                                             linearize(&**dows, "", ToString::to_string),
-                                             linearize(&**mons, "*", |&mon| (mon as u8).to_string()),
-                                             linearize(&**days, "*", ToString::to_string),
-                                             linearize(&**hrs, "*", ToString::to_string),
-                                             linearize(&**mins, "*", ToString::to_string)))
-                            })
-                        });
+    let shell_args = env.get("SHELL_ARGS").map(|v| v.trim()).filter(|v| !v.is_empty()).and_then(|v| {
+        if is_shell_command_flags(v) {
+            Some(v)
+        } else {
+            warnings.push(format!("ignoring invalid SHELL_ARGS, expected flags ending with -c: \"{}\"", v));
+            None
+        }
+    });
+    let description = source.comment
+                            .map(|comment| comment.trim().to_owned())
+                            .or_else(|| env.get("DESCRIPTION").map(|v| v.to_owned()))
+                            // a @shutdown line is parsed as a @reboot one, which it isn't to the reader
+                            .unwrap_or_else(|| if source.at_shutdown { format!("\"{}\"", line.trim()) } else { format!("\"{}\"", entry) });
+    let drop_in = env_flag(env, "DROP_IN");
+    let after_target = env.get("AFTER_TARGET").map(|v| v.trim()).filter(|v| !v.is_empty());
+    let at_shutdown = source.at_shutdown && entry.period() == Some(&Period::Reboot);
+    let early = match entry.period() {
+        Some(&Period::Reboot) => env_flag(env, "EARLY") && !at_shutdown,
+        _ => {
+            if env_flag(env, "EARLY") {
+                warnings.push("ignoring EARLY, only @reboot jobs can run early in the boot".to_owned());
+            }
+            false
+        }
+    };
+
+    // early jobs go in the generator directory for units that come first, if there's one
+    let dstdir = match config.early_dir {
+        Some(ref early_dir) if early => &**early_dir,
+        _ => &*config.dstdir,
+    };
+    let minutely_step = match env.get("MINUTELY_STEP").map(|v| v.trim()) {
+        None => 1,
+        Some(value) => {
+            match value.parse::<u64>() {
+                Ok(step) if (1..60).contains(&step) => step,
+                _ => {
+                    warnings.push(format!("ignoring invalid MINUTELY_STEP: \"{}\"", value));
+                    1
+                }
+            }
+        }
+    };
+    let service_type = match env.get("SERVICE_TYPE").map(|v| v.trim().to_lowercase()) {
+        None => "oneshot".to_owned(),
+        Some(value) => {
+            match &*value {
+                "simple" | "exec" | "forking" | "oneshot" | "dbus" | "notify" | "idle" => value,
+                _ => {
+                    warnings.push(format!("ignoring invalid SERVICE_TYPE: \"{}\"", value));
+                    "oneshot".to_owned()
+                }
+            }
+        }
+    };
+    let oncalendar = env.get("ONCALENDAR").map(|v| v.trim()).filter(|v| !v.is_empty()).and_then(|v| {
+        if is_calendar_expression(v) {
+            Some(v)
+        } else {
+            warnings.push(format!("ignoring ONCALENDAR, not a calendar expression: \"{}\"", v));
+            None
+        }
+    });
+    // the timer starts a unit of its own instead of a generated service
+    let triggers = env.get("TRIGGERS").map(|v| v.trim()).filter(|v| !v.is_empty()).and_then(|v| {
+        if !is_unit_name(v) || !v.contains('.') {
+            warnings.push(format!("ignoring TRIGGERS, not a valid unit name: \"{}\"", v));
+            None
+        } else if at_shutdown || early {
+            warnings.push("ignoring TRIGGERS, shutdown and early jobs have no timer".to_owned());
+            None
+        } else {
+            Some(v)
+        }
+    });
+    let installed = |name: &str| find_system_unit(name, config).map(|path| !is_generated_unit(&path)).unwrap_or(false);
+    let named = env.get("NAME").map(|v| v.trim()).filter(|v| !v.is_empty());
+    // with DROP_IN, NAME can be the installed service the job's keys go into,
+    // rather than an alias of a generated one
+    let drop_in_base = named.filter(|_| drop_in && triggers.is_none())
+                            .map(|v| if v.ends_with(".service") { v.to_owned() } else { format!("{}.service", v) })
+                            .filter(|name| is_unit_name(name) && installed(name));
+    let alias = named.filter(|_| drop_in_base.is_none()).and_then(|v| {
+        let name = if v.ends_with(".service") { v.to_owned() } else { format!("{}.service", v) };
+        if !is_unit_name(&name) {
+            warnings.push(format!("ignoring NAME, not a valid unit name: \"{}\"", v));
+            None
+        } else if installed(&name) {
+            warnings.push(format!("ignoring NAME, a unit named {} is already installed", name));
+            None
+        } else if triggers.is_some() {
+            warnings.push("ignoring NAME, with TRIGGERS there's no service to name".to_owned());
+            None
+        } else {
+            Some(name)
+        }
+    });
+
+    // anacron's own per-job delay is the fixed offset DELAY sets for crontabs,
+    // the spread of RANDOM_DELAY comes on top of it
+    let is_anacron = if let Anacron(AnacrontabEntry { delay: job_delay, .. }) = entry {
+        delay = job_delay as u64;
+        true
+    } else {
+        false
+    };
+
+    // neither catches up on missed runs, whatever PERSISTENT says
+    match entry.period() {
+        Some(&Period::Reboot) => {
+            if env_flag(env, "PERSISTENT") {
+                warnings.push("ignoring PERSISTENT, @reboot jobs only run at boot, there's no missed run to catch up on".to_owned());
+            }
+            if env.contains_key("START_HOURS_RANGE") {
+                warnings.push("ignoring START_HOURS_RANGE, @reboot jobs run at boot, not at an hour".to_owned());
+            }
+            persistent = false;
+            // the delay field of anacrontab is always given, and 0 there means right away
+            if delay == 0 && !is_anacron {
+                delay = 1;
+            }
+        }
+        Some(&Period::Minutely) => {
+            if env_flag(env, "PERSISTENT") {
+                warnings.push("ignoring PERSISTENT, a missed run of a @minutely job is caught up on by the next one".to_owned());
+            }
+            persistent = false;
+        }
+        _ => (),
+    }
+
+    let last_weekday = env_flag(env, "LAST_WEEKDAY");
+    let disabled = env_flag(env, "DISABLED");
+
+    // a delay of an hour or more moves the start of the periods of a day or more
+    // on by hours, but never into the next day, where a weekly or monthly job
+    // would run on another weekday or day of month than its period says
+    let (start_hour, start_minute) = match entry.period() {
+        Some(&Period::Reboot) | Some(&Period::Minutely) | None => (hour, delay),
+        Some(&Period::Hourly) | Some(&Period::Midnight) if delay > 59 => {
+            warnings.push(format!("the delay of {} minutes goes past the hour, the job starts at minute 59 instead", delay));
+            (hour, 59)
+        }
+        Some(&Period::Hourly) | Some(&Period::Midnight) => (hour, delay),
+        Some(_) if hour + delay / 60 > 23 => {
+            warnings.push(format!("the delay of {} minutes goes past the end of the day, the job starts at 23:59 instead",
+                                  delay));
+            (23, 59)
+        }
+        Some(_) => (hour + delay / 60, delay % 60),
+    };
 
-    if daemon_reload && schedule.is_none() {
-        warn!("skipping job from {}: \"{}\"", path.display(), entry);
-        return Ok(());
+    let schedule = match entry {
+        _ if oncalendar.is_some() => oncalendar.map(|calendar| vec![calendar.to_owned()]),
+        User(UserCrontabEntry { sched: Schedule::Period(Period::Minutely), .. }) |
+        System(SystemCrontabEntry { sched: Schedule::Period(Period::Minutely), .. }) if minutely_step > 1 => {
+            Some(vec![every_n_minutes(minutely_step)])
+        }
+        User(UserCrontabEntry { sched: Schedule::Period(Period::Hourly), .. }) |
+        System(SystemCrontabEntry { sched: Schedule::Period(Period::Hourly), .. }) if end_hour.is_some() => {
+            end_hour.map(|end| vec![hourly_within(hour, end, start_minute)])
+        }
+        User(UserCrontabEntry { sched: Schedule::Calendar(ref cal), .. }) |
+        System(SystemCrontabEntry { sched: Schedule::Calendar(ref cal), .. }) if last_weekday => {
+            last_weekday_oncalendar(cal).or_else(|| {
+                warnings.push("ignoring LAST_WEEKDAY, the job needs a day of week and no day of month".to_owned());
+                calendar_to_oncalendar(cal)
+            })
+        }
+        User(UserCrontabEntry { ref sched, .. }) |
+        System(SystemCrontabEntry { ref sched, .. }) => schedule_to_oncalendar(sched, start_minute, start_hour),
+        Anacron(AnacrontabEntry { ref period, .. }) => period_to_oncalendar(period, start_minute, start_hour),
+        EnvVar(_) => None,
+    };
+
+    let weekly_day = env.get("WEEKLY_DAY").map(|v| v.trim()).filter(|v| !v.is_empty()).and_then(|v| {
+        let day = weekday_name(v);
+        if day.is_none() {
+            warnings.push(format!("ignoring invalid WEEKLY_DAY, expected a day of week like Sun: \"{}\"", v));
+        }
+        day
+    });
+    let schedule = match (schedule, weekly_day, entry.period()) {
+        (Some(calendars), Some(day), Some(&Period::Weekly)) if oncalendar.is_none() => {
+            Some(calendars.iter().map(|calendar| on_weekday(calendar, day)).collect())
+        }
+        (schedule, _, _) => schedule,
+    };
+
+    let year = env.get("YEAR").map(|v| v.trim()).filter(|v| !v.is_empty()).and_then(|v| {
+        if v.starts_with(|c: char| c.is_ascii_digit()) && v.chars().all(|c| c.is_ascii_digit() || ",./".contains(c)) {
+            Some(v)
+        } else {
+            warnings.push(format!("ignoring invalid YEAR: \"{}\"", v));
+            None
+        }
+    });
+    let schedule = match (schedule, year) {
+        (Some(calendars), Some(year)) if oncalendar.is_none() => {
+            Some(calendars.iter().map(|calendar| with_year(calendar, year)).collect())
+        }
+        (schedule, _) => schedule,
+    };
+
+    // CRON_TZ is the zone of the schedules only, TZ that of the commands too, and
+    // the schedules as long as CRON_TZ doesn't say otherwise
+    let timezone = ["CRON_TZ", "TZ"].iter()
+                                    .filter_map(|&name| env.get(name).map(|v| (name, v.trim().trim_start_matches(':'))))
+                                    .find(|&(_, zone)| !zone.is_empty())
+                                    .and_then(|(name, zone)| {
+                                        if is_timezone(zone, config) {
+                                            Some(zone)
+                                        } else {
+                                            warnings.push(format!("ignoring {}, not a known time zone: \"{}\"", name, zone));
+                                            None
+                                        }
+                                    });
+    let schedule = match (schedule, timezone) {
+        (Some(calendars), Some(zone)) if oncalendar.is_none() => {
+            Some(calendars.iter().map(|calendar| format!("{} {}", spelled_out(calendar), zone)).collect())
+        }
+        (schedule, _) => schedule,
+    };
+
+    // ranges of numbers like 08..18 only came with systemd 236
+    let schedule = match schedule {
+        Some(calendars) if compat_before(config, 236) => Some(calendars.iter().map(|calendar| expand_ranges(calendar)).collect()),
+        schedule => schedule,
+    };
+
+    // WEEKLY_DAY=Mon or a year of * may have brought back a period systemd names
+    let schedule = match schedule {
+        Some(calendars) if oncalendar.is_none() => Some(calendars.iter().map(|calendar| named_calendar(calendar).to_owned()).collect()),
+        schedule => schedule,
+    };
+
+    // jitter is in minutes, or spans the whole time between two runs
+    let random_delay = match env.get("RANDOM_DELAY").map(|v| v.trim()) {
+        Some("random") | Some("period") => {
+            let length = if oncalendar.is_some() { None } else { period_length(&entry, minutely_step) };
+            if length.is_none() {
+                warnings.push("ignoring RANDOM_DELAY, the job has no period to spread over".to_owned());
+            }
+            length
+        }
+        Some(value) => value.parse::<u64>().ok().filter(|&minutes| minutes != 1).map(|minutes| format!("{}m", minutes)),
+        None => None,
+    };
+
+    if let Anacron(AnacrontabEntry { period: Period::Days(days), .. }) = entry {
+        if days >= YEARLY_DAYS && days != 365 && oncalendar.is_none() {
+            warnings.push(format!("anacrontab period of {} days is rounded to a year", days));
+        }
+    }
+
+    if let (Some(cal), None) = (entry.calendar(), oncalendar) {
+        if !calendar_can_fire(cal) {
+            warnings.push("the job will never run, no month matches its days".to_owned());
+        }
+    }
+
+    if config.reloading && schedule.is_none() && !at_shutdown {
+        warnings.push("skipping @reboot job on daemon reload".to_owned());
+        return Ok(Outcome::Skipped("@reboot job on daemon reload".to_owned()));
     }
 
+    // a job run at an interval after the end of its previous run has no calendar,
+    // only its first run is counted from boot, @reboot jobs don't repeat at all
+    let interval = match env.get("INTERVAL").map(|v| v.trim()) {
+        Some(interval) if schedule.is_some() && is_time_span(interval) => Some(interval),
+        Some(interval) if schedule.is_some() => {
+            warnings.push(format!("ignoring invalid INTERVAL: \"{}\"", interval));
+            None
+        }
+        _ => None,
+    };
+
+    // nor has a job run once, a while after its timer is started, rather than after boot
+    let run_after = match env.get("RUN_AFTER").map(|v| v.trim()) {
+        Some(run_after) if is_time_span(run_after) => Some(run_after),
+        Some(run_after) => {
+            warnings.push(format!("ignoring invalid RUN_AFTER: \"{}\"", run_after));
+            None
+        }
+        None => None,
+    };
+    let schedule = if interval.is_some() || run_after.is_some() { None } else { schedule };
+
     if let Some(cmd) = entry.command() {
 
+        // nothing to run, the ExecStart= would keep the service from loading at all
+        if split_stdin(cmd).0.trim().is_empty() {
+            warnings.push("skipping the line, there is no command after the schedule".to_owned());
+            return Ok(Outcome::Skipped("no command".to_owned()));
+        }
+
         // make sure we know the user
-        let user = try!(entry.user()
-                             .and_then(get_entry_by_name)
-                             .or_else(|| get_entry_by_uid(owner))
-                             .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unknown user")));
+        let user = entry.user()
+                        .and_then(|name| config.user_by_name(name))
+                        .or_else(|| config.user_by_uid(owner))
+                        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "unknown user"))?;
+
+        // units refer to the crontab as the system below the root sees it, and
+        // get the same names there as when it is generated at boot
+        let source_path = config.unrooted(path);
 
         // generate unique cron job id
         let mut md5ctx = ::md5::Context::new();
-        md5ctx.consume(path.as_os_str().as_bytes());
-        if let Some(ref schedule) = schedule {
-            md5ctx.consume(schedule.as_bytes());
+        md5ctx.consume(source_path.as_os_str().as_bytes());
+        for calendar in schedule.iter().flat_map(|cals| cals.iter()) {
+            md5ctx.consume(calendar.as_bytes());
+        }
+        if let Some(interval) = interval {
+            md5ctx.consume(interval.as_bytes());
+        }
+        if let Some(run_after) = run_after {
+            md5ctx.consume(run_after.as_bytes());
         }
         md5ctx.consume(cmd.as_bytes());
-        let md5hex = tohex(&md5ctx.compute());
+        let mut md5hex = tohex(&md5ctx.compute());
+
+        // identical jobs, in one file or across files, would overwrite each other's units,
+        // and a unit of the same name installed on the system would be shadowed, unless
+        // that is what DROP_IN is for
+        {
+            let mut unit_ids = config.unit_ids.lock().unwrap();
+            loop {
+                // the units --install wrote for this very job aren't another one's,
+                // they take the place of the generated ones
+                let installed = !drop_in &&
+                                ["service", "timer"].iter().any(|suffix| {
+                                    find_system_unit(&format!("{}-{}.{}", config.namespace, md5hex, suffix), config)
+                                        .map(|path| !is_generated_unit(&path))
+                                        .unwrap_or(false)
+                                });
+                let other = if installed {
+                    "an installed unit"
+                } else if !unit_ids.insert(md5hex.clone()) {
+                    "another job"
+                } else {
+                    break;
+                };
+                let mut md5ctx = ::md5::Context::new();
+                md5ctx.consume(md5hex.as_bytes());
+                let renamed = tohex(&md5ctx.compute());
+                warnings.push(format!("the job has the same unit name as {}, {}-{} is used instead of {}-{}",
+                                      other, config.namespace, renamed, config.namespace, md5hex));
+                md5hex = renamed;
+            }
+        }
 
         // create service and timer unit names
-        let service_unit_name = format!("cron-{}.service", md5hex);
-        let timer_unit_name = format!("cron-{}.timer", md5hex);
+        let service_unit_name = format!("{}-{}.service", config.namespace, md5hex);
+        let timer_unit_name = format!("{}-{}.timer", config.namespace, md5hex);
+
+        let alias = alias.and_then(|name| {
+            let mut aliases = config.aliases.lock().unwrap();
+            if aliases.contains_key(&name) {
+                warnings.push(format!("ignoring NAME, {} is already used by another job", name));
+                None
+            } else {
+                aliases.insert(name.clone(), service_unit_name.clone());
+                Some(name)
+            }
+        });
+
+        // units grouped by source go in a directory named after their crontab, and
+        // systemd, which only looks for them right in dstdir, finds them through links
+        let unit_subdir = if config.by_source { Some(source_dir_name(path, config)) } else { None };
+        let unit_dir = unit_subdir.as_ref().map_or_else(|| dstdir.to_owned(), |dir| dstdir.join(dir));
+        let relative = |name: &str| unit_subdir.as_ref().map_or_else(|| PathBuf::from(name), |dir| Path::new(dir).join(name));
 
         // unit paths
-        let service_unit_path = dstdir.join(&service_unit_name);
-        let timer_unit_path = dstdir.join(&timer_unit_name);
+        let service_unit_path = unit_dir.join(&service_unit_name);
+        let timer_unit_path = unit_dir.join(&timer_unit_name);
 
-        // make sure cron.target.wants dir exists
         let cron_target_wants_path = dstdir.join("cron.target.wants");
-        try!(create_dir_all(&cron_target_wants_path));
 
         // process command in case it should be put into script
-        let command = if metadata(cmd).map(|m| m.is_file()).unwrap_or(false) {
-            cmd.to_owned()
+        let script_dir = config.script_dir.as_ref().unwrap_or(&unit_dir);
+        let script_command_path = script_dir.join(format!("{}-{}.sh", config.namespace, md5hex));
+        let mut script_command_file = Vec::new();
+        let (cmd, mut stdin) = split_stdin(cmd);
+        // systemd before 236 can't feed a service data, the script does, with a here-document
+        let here_document = if compat_before(config, 236) { stdin.take().map(|text| here_document(&text)) } else { None };
+        // `FOO=bar command` sets FOO for that command only, which the service can do
+        // itself unless the shell runs the line anyway
+        let (cmd, inline_env) = if shell_args.is_none() {
+            split_inline_env(&cmd)
         } else {
-            let script_command_path = dstdir.join(format!("cron-{}.sh", md5hex));
-
-            debug!("generating script {:?} from {:?}", script_command_path, path);
-            {
-                let mut script_command_file = try!(File::create(&script_command_path));
-                try!(writeln!(script_command_file, "#!{}", shell));
-                try!(writeln!(script_command_file, "{}", cmd));
+            (cmd, Vec::new())
+        };
+        let command = match shell_args {
+            Some(shell_args) if here_document.is_none() => format!("{} {} {}", shell, shell_args, quote_exec_arg(&cmd)),
+            None if here_document.is_none() && metadata(config.rooted(&cmd)).map(|m| m.is_file()).unwrap_or(false) => cmd,
+            _ => {
+                debug!("generating script {:?} from {:?}", script_command_path, path);
+                writeln!(script_command_file, "#!{}", shell)?;
+                writeln!(script_command_file, "{}{}", cmd, here_document.unwrap_or_default())?;
+                let script = config.unrooted(&script_command_path).to_str().unwrap().to_owned();
+                // the shell runs the script with the flags it would have run the command with
+                match shell_args.map(script_flags).filter(|flags| !flags.is_empty()) {
+                    Some(flags) => format!("{} {} {}", shell, flags, script),
+                    None => script,
+                }
             }
+        };
 
-            let mut perms = try!(metadata(&script_command_path)).permissions();
-            perms.set_mode(0o755);
-            try!(set_permissions(&script_command_path, perms));
-            script_command_path.to_str().unwrap().to_owned()
+        // jobs sharing a lock file wait for each other, whichever units they are in
+        let command = match env.get("LOCK").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+            Some(lock) if lock.starts_with('/') => format!("/usr/bin/flock {} {}", quote_exec_arg(lock), command),
+            Some(lock) => {
+                warnings.push(format!("ignoring LOCK, expected an absolute path: \"{}\"", lock));
+                command
+            }
+            None => command,
         };
 
         debug!("generating service {:?} from {:?}", service_unit_path, path);
+        let mut service_unit_file = Vec::new();
         {
-            let mut service_unit_file = try!(File::create(service_unit_path));
 
-            try!(writeln!(service_unit_file, r###"[Unit]
-Description=[Cron] "{entry}"
+            writeln!(service_unit_file, r###"[Unit]
+Description=[Cron] {description}
 Documentation=man:systemd-crontab-generator(8)
-RefuseManualStart=true
-RefuseManualStop=true
 SourcePath={source_crontab_path}"###,
-                entry = entry,
-                source_crontab_path = path.display(),
-                ));
+                description = description,
+                source_crontab_path = source_path.display(),
+                )?;
+
+            // an alias is there to start the job by hand
+            if alias.is_none() {
+                writeln!(service_unit_file, "RefuseManualStart=true")?;
+                writeln!(service_unit_file, "RefuseManualStop=true")?;
+            }
+
+            if at_shutdown {
+                // without the default dependencies, there's no Conflicts=shutdown.target
+                // stopping the job as soon as it is started, nor cron.target stopping it
+                writeln!(service_unit_file, "DefaultDependencies=no")?;
+                writeln!(service_unit_file, "Before=shutdown.target")?;
+            } else if early {
+                // the defaults would order the job after basic.target, this is before sysinit.target
+                writeln!(service_unit_file, "DefaultDependencies=no")?;
+                writeln!(service_unit_file, "Conflicts=shutdown.target")?;
+                writeln!(service_unit_file, "After=local-fs.target")?;
+                writeln!(service_unit_file, "Before=sysinit.target shutdown.target")?;
+            } else {
+                writeln!(service_unit_file, "PartOf=cron.target")?;
+            }
+
+            // an empty MAILTO disables mail, but empty list items are likely typos
+            if let Some(mailto) = env.get("MAILTO").filter(|mailto| !mailto.trim().is_empty()) {
+                for recipient in mailto.split(',').map(str::trim) {
+                    if recipient.is_empty() {
+                        warnings.push(format!("empty recipient in MAILTO: \"{}\"", mailto));
+                    } else if !recipient.chars().all(|c| c.is_alphanumeric() || "@._+-".contains(c)) {
+                        warnings.push(format!("MAILTO recipient doesn't look like an address or user name: \"{}\"", recipient));
+                    }
+                }
+                writeln!(service_unit_file, "OnFailure=cron-failure@%i.service")?;
+            }
+
+            // a missing condition path silently skips the job, a missing assert path fails it;
+            // systemd takes absolute paths, negated with a leading `!`
+            for &(name, directive) in [("REQUIRE_PATH", "ConditionPathExists"), ("ASSERT_PATH", "AssertPathExists")].iter() {
+                match env.get(name).map(|v| v.trim()).filter(|v| !v.is_empty()) {
+                    Some(path) if path.starts_with('/') || path.starts_with("!/") => {
+                        writeln!(service_unit_file, "{}={}", directive, path)?;
+                    }
+                    Some(path) => warnings.push(format!("ignoring {}, expected an absolute path: \"{}\"", name, path)),
+                    None => (),
+                }
+            }
+            if env_flag(env, "FIRST_BOOT_ONLY") {
+                writeln!(service_unit_file, "ConditionFirstBoot=true")?;
+            }
+            if env_flag(env, "KEEP_ON_ISOLATE") {
+                writeln!(service_unit_file, "IgnoreOnIsolate=true")?;
+            }
 
-            if env.contains_key("MAILTO") {
-                try!(writeln!(service_unit_file, "OnFailure=cron-failure@%i.service"));
+            // the deadline is on the job the timer queues to start the service,
+            // which may wait on dependencies or an ordering, not on the timer
+            if let Some(timeout) = env.get("JOB_TIMEOUT").map(|v| v.trim()) {
+                if is_time_span(timeout) {
+                    writeln!(service_unit_file, "JobTimeoutSec={}", timeout)?;
+                } else {
+                    warnings.push(format!("ignoring invalid JOB_TIMEOUT: \"{}\"", timeout));
+                }
+            }
+
+            if let (None, Some(target)) = (schedule.as_ref(), after_target) {
+                writeln!(service_unit_file, "Wants={}", target)?;
+                writeln!(service_unit_file, "After={}", target)?;
+            }
+
+            // jobs named with NAME, resolved once all crontabs are read, see `Dependency`
+            for &var in ["REQUIRES", "AFTER", "CONFLICTS"].iter() {
+                for name in env.get(var).iter().flat_map(|v| v.split(|c: char| c == ',' || c.is_whitespace())).filter(|n| !n.is_empty()) {
+                    config.dependencies.lock().unwrap().push(Dependency {
+                        path: path.to_owned(),
+                        lineno,
+                        dstdir: dstdir.to_owned(),
+                        unit: service_unit_name.clone(),
+                        var,
+                        name: name.to_owned(),
+                    });
+                }
             }
 
             if user.uid != 0 {
-                try!(writeln!(service_unit_file, "Requires=systemd-user-sessions.service"));
+                if !at_shutdown && !early {
+                    writeln!(service_unit_file, "Requires=systemd-user-sessions.service")?;
+                }
                 if !user.dir.is_empty() {
-                    try!(writeln!(service_unit_file, "RequiresMountsFor={}", user.dir));
+                    writeln!(service_unit_file, "RequiresMountsFor={}", user.dir)?;
                 }
             }
 
-            try!(writeln!(service_unit_file, r###"
-[Service]
-Type=oneshot
+            // the job waits for the file systems it needs to be mounted
+            for mount in env.get("REQUIRE_MOUNT").iter().flat_map(|v| v.split_whitespace()) {
+                if mount.starts_with('/') {
+                    writeln!(service_unit_file, "RequiresMountsFor={}", mount)?;
+                } else {
+                    warnings.push(format!("ignoring REQUIRE_MOUNT, expected an absolute path: \"{}\"", mount));
+                }
+            }
+
+        }
+
+        let mut service_section = Vec::new();
+        {
+            writeln!(service_section, r###"Type={service_type}
 IgnoreSIGPIPE=false
 ExecStart={command}"###,
+                service_type = service_type,
                 command = command,
-                ));
+                )?;
+
+            if let Some(ref stdin) = stdin {
+                // base64 keeps the text exact, and chunks of it keep the lines short
+                writeln!(service_section, "StandardInput=data")?;
+                for chunk in stdin.as_bytes().chunks(384) {
+                    writeln!(service_section, "StandardInputData={}", chunk.to_base64(STANDARD))?;
+                }
+            }
+
+            // the error output always goes to the journal, to see why a job failed
+            match env.get("OUTPUT").map(|v| v.trim().to_lowercase()) {
+                Some(ref output) if output == "null" => writeln!(service_section, "StandardOutput=null")?,
+                Some(ref output) if output == "journal" || output.is_empty() => (),
+                Some(output) => warnings.push(format!("ignoring invalid OUTPUT: \"{}\"", output)),
+                None => (),
+            }
 
             if schedule.is_some() && delay > 0 {
-                try!(writeln!(service_unit_file, "ExecStartPre=-{}/{}/boot-delay {}", LIB_DIR, PACKAGE, delay));
+                writeln!(service_section, "ExecStartPre=-{}/{}/boot-delay {}", LIB_DIR, PACKAGE, delay)?;
+            }
+
+            // only run once ExecStart= succeeded
+            if let Some(touch) = env.get("TOUCH_ON_SUCCESS").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+                if touch.starts_with('/') {
+                    writeln!(service_section, "ExecStartPost=/bin/touch {}", quote_exec_arg(touch))?;
+                } else {
+                    warnings.push(format!("ignoring TOUCH_ON_SUCCESS, expected an absolute path: \"{}\"", touch));
+                }
             }
 
             if user.uid != 0 {
-                try!(writeln!(service_unit_file, "User={}", user.name));
-                try!(writeln!(service_unit_file, "WorkingDirectory=~"));
+                writeln!(service_section, "User={}", user.name)?;
+                writeln!(service_section, "WorkingDirectory=~")?;
             }
 
             if let Some(group) = entry.group() {
-                try!(writeln!(service_unit_file, "Group={}", group));
+                writeln!(service_section, "Group={}", group)?;
             }
             if batch {
-                try!(writeln!(service_unit_file, "CPUSchedulingPolicy=idle"));
-                try!(writeln!(service_unit_file, "IOSchedulingClass=idle"));
+                writeln!(service_section, "CPUSchedulingPolicy=idle")?;
+                writeln!(service_section, "IOSchedulingClass=idle")?;
+            }
+
+            // systemd creates a missing slice on its own, no unit needed
+            if let Some(slice) = env.get("SLICE").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+                if slice.ends_with(".slice") && is_unit_name(slice) {
+                    writeln!(service_section, "Slice={}", slice)?;
+                } else {
+                    warnings.push(format!("ignoring invalid SLICE, expected a unit name like cron.slice: \"{}\"", slice));
+                }
+            }
+
+            if let Some(cpu_quota) = env.get("CPU_QUOTA").map(|v| v.trim()) {
+                let valid = cpu_quota.ends_with('%') &&
+                            cpu_quota[..cpu_quota.len() - 1].parse::<f64>().map(|q| q > 0.0).unwrap_or(false);
+                if valid {
+                    writeln!(service_section, "CPUQuota={}", cpu_quota)?;
+                } else {
+                    warnings.push(format!("ignoring invalid CPU_QUOTA, expected a percentage: \"{}\"", cpu_quota));
+                }
+            }
+
+            for &(name, directive) in [("MEMORY_HIGH", "MemoryHigh"), ("MEMORY_MAX", "MemoryMax")].iter() {
+                if let Some(size) = env.get(name).map(|v| v.trim()) {
+                    if is_byte_size(size) {
+                        writeln!(service_section, "{}={}", directive, size)?;
+                    } else {
+                        warnings.push(format!("ignoring invalid {}, expected a size like 512M: \"{}\"", name, size));
+                    }
+                }
+            }
+
+            if let Some(protect_home) = env.get("PROTECT_HOME") {
+                match &*protect_home.trim().to_lowercase() {
+                    value @ "yes" | value @ "no" | value @ "true" | value @ "false" | value @ "read-only" | value @ "tmpfs" => {
+                        writeln!(service_section, "ProtectHome={}", value)?;
+                    }
+                    value => warnings.push(format!("ignoring invalid PROTECT_HOME: \"{}\"", value)),
+                }
+            }
+
+            // the files the job creates get the permissions it relies on, not 0022's
+            if let Some(umask) = env.get("UMASK").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+                if umask.len() <= 4 && umask.chars().all(|c| ('0'..='7').contains(&c)) {
+                    writeln!(service_section, "UMask={:0>4}", umask)?;
+                } else {
+                    warnings.push(format!("ignoring invalid UMASK, expected an octal mask like 0027: \"{}\"", umask));
+                }
+            }
+
+            if let Some(runtime_max) = env.get("RUNTIME_MAX").map(|v| v.trim()) {
+                if is_time_span(runtime_max) {
+                    writeln!(service_section, "RuntimeMaxSec={}", runtime_max)?;
+                } else {
+                    warnings.push(format!("ignoring invalid RUNTIME_MAX: \"{}\"", runtime_max));
+                }
+            }
+
+            // the pings come from a job that keeps running, not one that is done once it exits
+            if let Some(watchdog) = env.get("WATCHDOG").map(|v| v.trim()) {
+                if !is_time_span(watchdog) {
+                    warnings.push(format!("ignoring invalid WATCHDOG: \"{}\"", watchdog));
+                } else if service_type == "oneshot" {
+                    warnings.push("ignoring WATCHDOG, oneshot jobs aren't watched, set SERVICE_TYPE as well".to_owned());
+                } else {
+                    writeln!(service_section, "WatchdogSec={}", watchdog)?;
+                }
+            }
+
+            // systemd refuses to restart oneshot jobs that succeeded, they'd never be done
+            if let Some(restart) = env.get("RESTART").map(|v| v.trim().to_lowercase()) {
+                match &*restart {
+                    "always" | "on-success" if service_type == "oneshot" => {
+                        warnings.push(format!("ignoring RESTART={}, oneshot jobs can only be restarted on failure", restart))
+                    }
+                    "no" | "always" | "on-success" | "on-failure" | "on-abnormal" | "on-watchdog" | "on-abort" => {
+                        writeln!(service_section, "Restart={}", restart)?;
+                    }
+                    _ => warnings.push(format!("ignoring invalid RESTART: \"{}\"", restart)),
+                }
+            }
+            if let Some(restart_sec) = env.get("RESTART_SEC").map(|v| v.trim()) {
+                if is_time_span(restart_sec) {
+                    writeln!(service_section, "RestartSec={}", restart_sec)?;
+                } else {
+                    warnings.push(format!("ignoring invalid RESTART_SEC: \"{}\"", restart_sec));
+                }
+            }
+
+            if let Some(success_exit) = env.get("SUCCESS_EXIT") {
+                let (statuses, invalid): (Vec<&str>, Vec<&str>) =
+                    success_exit.split(|c: char| c == ',' || c.is_whitespace())
+                                .filter(|s| !s.is_empty())
+                                .partition(|s| is_exit_status(s));
+                for status in invalid {
+                    warnings.push(format!("ignoring invalid exit status in SUCCESS_EXIT: \"{}\"", status));
+                }
+                if !statuses.is_empty() {
+                    writeln!(service_section, "SuccessExitStatus={}", statuses.join(" "))?;
+                }
+            }
+
+            if let Some(hardening) = env.get("HARDENING") {
+                match &*hardening.trim().to_lowercase() {
+                    "strict" => {
+                        for setting in STRICT_HARDENING.iter() {
+                            writeln!(service_section, "{}", setting)?;
+                        }
+                    }
+                    "" | "none" => (),
+                    value => warnings.push(format!("ignoring unknown HARDENING profile: \"{}\"", value)),
+                }
+            }
+
+            if let Some(paths) = env.get("READ_WRITE_PATHS") {
+                // a leading "-" lets the path be missing, a leading "+" ignores RootDirectory=
+                let (paths, invalid): (Vec<&str>, Vec<&str>) =
+                    paths.split(|c: char| c == ',' || c.is_whitespace())
+                         .filter(|p| !p.is_empty())
+                         .partition(|p| p.trim_start_matches(['-', '+']).starts_with('/'));
+                for path in invalid {
+                    warnings.push(format!("ignoring relative path in READ_WRITE_PATHS: \"{}\"", path));
+                }
+                if !paths.is_empty() {
+                    writeln!(service_section, "ReadWritePaths={}", paths.join(" "))?;
+                }
+            }
+
+            // a leading "-" lets the file be missing
+            if let Some(env_file) = env.get("ENV_FILE").map(|v| v.trim()).filter(|v| !v.is_empty()) {
+                if env_file.trim_start_matches('-').starts_with('/') {
+                    writeln!(service_section, "EnvironmentFile={}", env_file)?;
+                } else {
+                    warnings.push(format!("ignoring ENV_FILE, expected an absolute path: \"{}\"", env_file));
+                }
             }
 
-            if !env.is_empty() {
-                for (name, value) in env.iter() {
-                    try!(writeln!(service_unit_file, r#"Environment="{}={}""#, name, value));
+            // mail-on-failure reads the mail settings from the job's environment
+            let allowed = env.get("ALLOWED_ENV").map(|names| {
+                names.split(|c: char| c == ',' || c.is_whitespace())
+                     .filter(|n| !n.is_empty())
+                     .chain(["MAILTO", "MAIL_COMMAND"].iter().cloned())
+                     .collect::<BTreeSet<_>>()
+            });
+            let overridden = |name: &str| inline_env.iter().any(|(assigned, _)| assigned == name);
+            for (name, value) in env.iter().filter(|&(name, _)| !CONTROL_VARS.contains(&&**name) && !overridden(name)) {
+                if allowed.as_ref().map(|allowed| allowed.contains(&**name)).unwrap_or(true) {
+                    writeln!(service_section, r#"Environment="{}={}""#, name, escape_env_value(value))?;
                 }
             }
+            // instead of the crontab's variables of the same name
+            for (name, value) in inline_env.iter() {
+                writeln!(service_section, r#"Environment="{}={}""#, name, escape_env_value(value))?;
+            }
         }
 
         debug!("generating timer {:?} from {:?}", timer_unit_path, path);
+        // the crontab line the timer comes from, as it was written
+        let origin = format!("# {}:{}\n# {}\n", source_path.display(), lineno, line);
+
+        let mut timer_unit_file = Vec::new();
         {
-            let mut timer_unit_file = try!(File::create(&timer_unit_path));
 
-            try!(writeln!(timer_unit_file, r###"[Unit]
-Description=[Timer] "{entry}"
+            writeln!(timer_unit_file, r###"[Unit]
+Description=[Timer] {description}
 Documentation=man:systemd-crontab-generator(8)
 PartOf=cron.target
 RefuseManualStart=true
 RefuseManualStop=true
-SourcePath={source_crontab_path}
+SourcePath={source_crontab_path}"###,
+                description = description,
+                source_crontab_path = source_path.display(),
+                )?;
 
-[Timer]
-Unit={service_unit_name}"###,
-                entry = entry,
-                source_crontab_path = path.display(),
-                service_unit_name = service_unit_name,
-                ));
+            if let (None, Some(target)) = (schedule.as_ref(), after_target) {
+                writeln!(timer_unit_file, "After={}", target)?;
+            }
+        }
 
+        let mut timer_section = Vec::new();
+        {
             if cfg![feature = "persistent"] {
-                try!(writeln!(timer_unit_file, "Persistent={}", persistent));
+                writeln!(timer_section, "Persistent={}", persistent)?;
             }
 
-            if let Some(schedule) = schedule {
-                try!(writeln!(timer_unit_file, "OnCalendar={}", schedule));
+            if let Some(ref schedule) = schedule {
+                for calendar in schedule {
+                    writeln!(timer_section, "OnCalendar={}", calendar)?;
+                }
+            } else if let Some(run_after) = run_after {
+                writeln!(timer_section, "OnActiveSec={}", run_after)?;
+            } else if after_target.is_some() {
+                // the timer is ordered after the target, so this counts from reaching it
+                writeln!(timer_section, "OnActiveSec={}m", delay)?;
             } else {
-                try!(writeln!(timer_unit_file, "OnBootSec={}m", delay));
+                writeln!(timer_section, "OnBootSec={}m", delay)?;
+            }
+            if let Some(interval) = interval {
+                writeln!(timer_section, "OnUnitInactiveSec={}", interval)?;
             }
 
-            if random_delay != 1 {
-                if cfg!(feature="randomized-delay") {
-                    try!(writeln!(timer_unit_file, "RandomizedDelaySec={}m", random_delay));
+            if let Some(ref random_delay) = random_delay {
+                if cfg!(feature="randomized-delay") && !compat_before(config, 229) {
+                    writeln!(timer_section, "RandomizedDelaySec={}", random_delay)?;
+                    if env_flag(env, "FIXED_RANDOM_DELAY") {
+                        if compat_before(config, 247) {
+                            warnings.push("ignoring FIXED_RANDOM_DELAY, FixedRandomDelay= needs systemd 247".to_owned());
+                        } else {
+                            writeln!(timer_section, "FixedRandomDelay=true")?;
+                        }
+                    }
+                    // lets systemd fire the job along with others anywhere in the same window
+                    if env_flag(env, "COALESCE") {
+                        writeln!(timer_section, "AccuracySec={}", random_delay)?;
+                    }
                 } else {
-                    try!(writeln!(timer_unit_file, "AccuracySec={}m", random_delay));
+                    writeln!(timer_section, "AccuracySec={}", random_delay)?;
                 }
             }
         }
-        try!(symlink(timer_unit_path, cron_target_wants_path.join(timer_unit_name)));
+
+        // the units the keys of the job go into with DROP_IN, if they are installed
+        let base_service_name = drop_in_base.unwrap_or_else(|| service_unit_name.clone());
+        let base_timer_name = format!("{}.timer", base_service_name.trim_end_matches(".service"));
+
+        let timer_target = triggers.map(str::to_owned).unwrap_or_else(|| base_service_name.clone());
+
+        if config.dry_run {
+            let mut units = Vec::new();
+            if !script_command_file.is_empty() && triggers.is_none() {
+                units.push((format!("{}-{}.sh", config.namespace, md5hex), String::from_utf8_lossy(&script_command_file).into_owned()));
+            }
+            if triggers.is_none() {
+                units.push((service_unit_name.clone(), render_unit(&[&service_unit_file, b"\n[Service]\n", &service_section])));
+            }
+            if !at_shutdown && !early {
+                units.push((timer_unit_name.clone(),
+                            render_unit(&[origin.as_bytes(),
+                                          &timer_unit_file,
+                                          format!("\n[Timer]\nUnit={}\n", timer_target).as_bytes(),
+                                          &timer_section])));
+            }
+            return Ok(Outcome::Generated(schedule.unwrap_or_default(), Vec::new(), units));
+        }
+
+        let mut files = Vec::new();
+
+        // make sure cron.target.wants dir exists
+        create_dir_all(&cron_target_wants_path)?;
+        create_dir_all(&unit_dir)?;
+
+        if !script_command_file.is_empty() && triggers.is_none() {
+            create_dir_all(script_dir)?;
+            write_file(&script_command_path, &[&script_command_file])?;
+            let mut perms = metadata(&script_command_path)?.permissions();
+            perms.set_mode(0o755);
+            set_permissions(&script_command_path, perms)?;
+            files.push(match config.script_dir {
+                Some(_) => script_command_path.clone(),
+                None => relative(&format!("{}-{}.sh", config.namespace, md5hex)),
+            });
+        }
+
+        let link_from_dstdir = |unit_path: PathBuf, name: &str, files: &mut Vec<PathBuf>| -> io::Result<PathBuf> {
+            if unit_subdir.is_none() {
+                return Ok(unit_path);
+            }
+            let link_path = dstdir.join(name);
+            replace_symlink(&config.unrooted(&unit_path), &link_path)?;
+            files.push(PathBuf::from(name));
+            Ok(link_path)
+        };
+
+        // with DROP_IN set, units already installed on the system only get their
+        // [Service]/[Timer] keys overridden, so local customizations are kept
+        let base_unit_path = |name: &str| if drop_in {
+            find_system_unit(name, config).filter(|path| !is_generated_unit(path))
+        } else {
+            None
+        };
+
+        let service_unit_path = match base_unit_path(&base_service_name) {
+            _ if triggers.is_some() => service_unit_path,
+            Some(base_service_unit_path) => {
+                files.push(write_drop_in(dstdir, &base_service_name, "override.conf", &[b"[Service]\nExecStart=\n", &service_section])?);
+                base_service_unit_path
+            }
+            None => {
+                write_unit(&service_unit_path, &[&service_unit_file, b"\n[Service]\n", &service_section])?;
+                files.push(relative(&service_unit_name));
+                link_from_dstdir(service_unit_path, &service_unit_name, &mut files)?
+            }
+        };
+
+        if let Some(ref alias) = alias {
+            replace_symlink(&config.unrooted(&service_unit_path), &dstdir.join(alias))?;
+            files.push(PathBuf::from(alias));
+        }
+
+        if at_shutdown || early {
+            // no timer, the job is started along with shutdown.target, or sysinit.target
+            if !disabled {
+                let wants = if early { "sysinit.target.wants" } else { "shutdown.target.wants" };
+                create_dir_all(dstdir.join(wants))?;
+                replace_symlink(&config.unrooted(&service_unit_path), &dstdir.join(wants).join(&base_service_name))?;
+                files.push(Path::new(wants).join(&base_service_name));
+            }
+            // files outside of the output directory are listed with their whole path
+            if dstdir != &*config.dstdir {
+                files = files.into_iter().map(|file| dstdir.join(file)).collect();
+            }
+            return Ok(Outcome::Generated(Vec::new(), files, Vec::new()));
+        }
+
+        let base_timer_unit_path = base_unit_path(&base_timer_name);
+        let timer_unit_name = if base_timer_unit_path.is_some() { base_timer_name } else { timer_unit_name };
+        let timer_unit_path = match base_timer_unit_path {
+            Some(base_timer_unit_path) => {
+                let reset: &[u8] = if schedule.is_some() {
+                    b"[Timer]\nOnCalendar=\n"
+                } else if run_after.is_some() || after_target.is_some() {
+                    b"[Timer]\nOnActiveSec=\n"
+                } else {
+                    b"[Timer]\nOnBootSec=\n"
+                };
+                files.push(write_drop_in(dstdir, &timer_unit_name, "override.conf", &[origin.as_bytes(), reset, &timer_section])?);
+                base_timer_unit_path
+            }
+            None => {
+                write_unit(&timer_unit_path,
+                           &[origin.as_bytes(),
+                             &timer_unit_file,
+                             format!("\n[Timer]\nUnit={}\n", timer_target).as_bytes(),
+                             &timer_section])?;
+                files.push(relative(&timer_unit_name));
+                link_from_dstdir(timer_unit_path, &timer_unit_name, &mut files)?
+            }
+        };
+
+        // a disabled job's timer is there to look at, but nothing starts it
+        if !disabled {
+            replace_symlink(&config.unrooted(&timer_unit_path), &cron_target_wants_path.join(&timer_unit_name))?;
+            files.push(Path::new("cron.target.wants").join(&timer_unit_name));
+        }
+
+        return Ok(Outcome::Generated(schedule.unwrap_or_default(), files, Vec::new()));
     }
 
-    Ok(())
+    Ok(Outcome::Generated(schedule.unwrap_or_default(), Vec::new(), Vec::new()))
 }
 
-fn linearize<T, C>(input: &[Interval<T>], star: &str, conv: C) -> String
-    where T: Limited,
-          C: Fn(&T) -> String
-{
-    if input.len() == 1 && input[0] == Interval::Full(1) {
-        star.to_owned()
-    } else {
-        let mut output = String::new();
-        for part in input.iter().flat_map(|v| v.iter()).collect::<BTreeSet<_>>().iter() {
-            output.push_str(&*conv(&part));
-            output.push(',');
-        }
-        output.pop();
-        output
+/// Translates a crontab schedule into the `OnCalendar=` expressions of its timer,
+/// taking the `DELAY` (in minutes) and `START_HOURS_RANGE` start hour into account
+/// for periods. `@daily` becomes `daily`, or `*-*-* 06:05:00` with a delay of 5
+/// and hour 6; `0 9,17 * * 1-5` becomes `Mon..Fri *-*-* 09,17:00:00`, while
+/// calendars equal to a named period, like `0 0 1 1 *`, get its name and
+/// `*/10 * * * *` becomes `*:0/10`. `@reboot` has no calendar expression and
+/// yields `None`.
+pub fn schedule_to_oncalendar(sched: &Schedule, delay: u64, hour: u64) -> Option<Vec<String>> {
+    match *sched {
+        Schedule::Period(ref period) => period_to_oncalendar(period, delay, hour),
+        Schedule::Calendar(ref cal) => calendar_to_oncalendar(cal),
     }
 }
 
+/// How each field of a crontab calendar, in crontab order, is written in the
+/// `OnCalendar=` expression translated from it, e.g. `("day of week", "Mon..Fri")`.
+pub fn calendar_fields(cal: &Calendar) -> Vec<(&'static str, String)> {
+    let Calendar { ref dows, ref days, ref mons, ref hrs, ref mins } = *cal;
+
+    let dows = linearize_dows(dows);
+    vec![("minute", linearize(mins, "*", |min| format!("{:0>2}", min.to_string()))),
+         ("hour", linearize(hrs, "*", |hr| format!("{:0>2}", hr.to_string()))),
+         ("day of month", linearize(days, "*", ToString::to_string)),
+         ("month", linearize(mons, "*", |&mon| (mon as u8).to_string())),
+         ("day of week", if dows.is_empty() { "*".to_owned() } else { dows })]
+}
+
+/// Calendar expression of a job run every `step` minutes, on the minutes
+/// divisible by it.
+fn every_n_minutes(step: u64) -> String {
+    if step == 1 { "minutely".to_owned() } else { format!("*:0/{}", step) }
+}
+
+fn period_to_oncalendar(period: &Period, delay: u64, hour: u64) -> Option<Vec<String>> {
+    match *period {
+        Period::Reboot => None,
+        Period::Minutely => Some(every_n_minutes(1)),
+        Period::Hourly => if delay == 0 { Some("hourly".to_owned()) } else { Some(format!("*-*-* *:{:02}:00", delay)) },
+        Period::Midnight => {
+            if delay == 0 { Some("daily".to_owned()) } else { Some(format!("*-*-* 00:{:02}:00", delay)) }
+        }
+        Period::Daily => {
+            if delay == 0 && hour == 0 {
+                Some("daily".to_owned())
+            } else {
+                Some(format!("*-*-* {:02}:{:02}:00", hour, delay))
+            }
+        }
+        Period::Weekly => {
+            if delay == 0 && hour == 0 {
+                Some("weekly".to_owned())
+            } else {
+                Some(format!("Mon *-*-* {:02}:{:02}:00", hour, delay))
+            }
+        }
+        Period::Monthly => {
+            if delay == 0 && hour == 0 {
+                Some("monthly".to_owned())
+            } else {
+                Some(format!("*-*-1 {:02}:{:02}:00", hour, delay))
+            }
+        }
+        Period::Quaterly => {
+            if delay == 0 && hour == 0 {
+                Some("quarterly".to_owned())
+            } else {
+                Some(format!("*-1,4,7,10-1 {:02}:{:02}:00", hour, delay))
+            }
+        }
+        Period::Biannually => {
+            if delay == 0 && hour == 0 {
+                Some("semiannually".to_owned())
+            } else {
+                Some(format!("*-1,7-1 {:02}:{:02}:00", hour, delay))
+            }
+        }
+        Period::Yearly => {
+            if delay == 0 && hour == 0 {
+                Some("yearly".to_owned())
+            } else {
+                Some(format!("*-1-1 {:02}:{:02}:00", hour, delay))
+            }
+        }
+        Period::Days(days) => {
+            // workaround for anacrontab
+            if days >= YEARLY_DAYS {
+                Some(format!("*-1-1 {:02}:{:02}:00", hour, delay))
+            } else if days > 31 {
+                Some(format!("*-1/{}-1 {:02}:{:02}:00", days / 30, hour, delay))
+            } else {
+                Some(format!("*-*-1/{} {:02}:{:02}:00", days, hour, delay))
+            }
+        }
+    }.map(|calendar| vec![calendar])
+}
+
+fn calendar_to_oncalendar(cal: &Calendar) -> Option<Vec<String>> {
+    let Calendar { ref dows, ref days, ref mons, ref hrs, ref mins } = *cal;
+
+    // every n minutes, the most common of all, in its short form
+    let minutes = mins.iter()
+                      .flat_map(interval_values)
+                      .filter_map(|min| min.to_string().parse::<u64>().ok())
+                      .collect::<BTreeSet<_>>()
+                      .into_iter()
+                      .collect::<Vec<_>>();
+    if minutes.len() > 1 && linearize(hrs, "*", ToString::to_string) == "*" &&
+       linearize(days, "*", ToString::to_string) == "*" && linearize_dows(dows).is_empty() &&
+       linearize(mons, "*", |&mon| (mon as u8).to_string()) == "*" {
+        let step = minutes[1] - minutes[0];
+        if minutes.windows(2).all(|w| w[1] - w[0] == step) && minutes[minutes.len() - 1] + step > 59 {
+            return Some(vec![if minutes[0] == 0 { every_n_minutes(step) } else { format!("*:{}/{}", minutes[0], step) }]);
+        }
+    }
+
+    let calendar = format!("{} *-{}-{} {}:{}:00",
+                           linearize_dows(dows),
+                           linearize(mons, "*", |&mon| (mon as u8).to_string()),
+                           linearize_days(days),
+                           linearize(hrs, "*", |hr| format!("{:0>2}", hr.to_string())),
+                           linearize(mins, "*", |min| format!("{:0>2}", min.to_string())));
+
+    Some(vec![named_calendar(calendar.trim()).to_owned()])
+}
+
+/// Calendar expression of an hourly job only run from the `start` to the `end`
+/// hour of the day, `*-*-* 08..18:00:00` for 8-18. A range past midnight, like
+/// 22-6, wraps around.
+fn hourly_within(start: u64, end: u64, delay: u64) -> String {
+    let hours = if start <= end {
+        format!("{:02}..{:02}", start, end)
+    } else {
+        format!("{:02}..23,00..{:02}", start, end)
+    };
+    format!("*-*-* {}:{:02}:00", hours, delay)
+}
+
+/// Gives the calendar expressions systemd has a name for that name, the spelled
+/// out periods read better as `daily` than as `*-*-* 00:00:00`.
+fn named_calendar(calendar: &str) -> &str {
+    match calendar {
+        "*-*-* *:*:00" => "minutely",
+        "*-*-* *:00:00" => "hourly",
+        "*-*-* 00:00:00" => "daily",
+        "Mon *-*-* 00:00:00" => "weekly",
+        "*-*-1 00:00:00" => "monthly",
+        "*-1,4,7,10-1 00:00:00" => "quarterly",
+        "*-1,7-1 00:00:00" => "semiannually",
+        "*-1-1 00:00:00" => "yearly",
+        calendar => calendar,
+    }
+}
+
+/// Spells out the calendar expressions systemd has a name for, `daily` is
+/// `*-*-* 00:00:00`, so they can be added to.
+fn spelled_out(calendar: &str) -> &str {
+    match calendar {
+        "minutely" => "*-*-* *:*:00",
+        "hourly" => "*-*-* *:00:00",
+        "daily" => "*-*-* 00:00:00",
+        "weekly" => "Mon *-*-* 00:00:00",
+        "monthly" => "*-*-1 00:00:00",
+        "quarterly" => "*-1,4,7,10-1 00:00:00",
+        "semiannually" => "*-1,7-1 00:00:00",
+        "yearly" => "*-1-1 00:00:00",
+        calendar => calendar,
+    }
+}
+
+/// Moves a weekly calendar expression to another day of week: `weekly` on
+/// `Sun` becomes `Sun *-*-* 00:00:00`.
+fn on_weekday(calendar: &str, day: &str) -> String {
+    let calendar = spelled_out(calendar);
+    format!("{}{}", day, calendar.trim_start_matches(|c: char| c.is_ascii_alphabetic()))
+}
+
+/// Whether the units are for a systemd older than `version`, see `--compat`.
+fn compat_before(config: &GeneratorConfig, version: u32) -> bool {
+    config.compat.map(|compat| compat < version).unwrap_or(false)
+}
+
+/// Spells out the ranges of numbers in a calendar expression as lists, keeping
+/// their zero padding: `*-*-* 08..11:00:00` becomes `*-*-* 08,09,10,11:00:00`.
+/// Weekday ranges like `Mon..Fri` are left alone.
+fn expand_ranges(calendar: &str) -> String {
+    let expand = |token: &str| {
+        let mut bounds = token.splitn(2, "..");
+        match (bounds.next(), bounds.next()) {
+            (Some(first), Some(last)) => {
+                match (first.parse::<u32>(), last.parse::<u32>()) {
+                    (Ok(start), Ok(end)) if start <= end => {
+                        (start..end + 1).map(|n| format!("{:01$}", n, first.len())).collect::<Vec<_>>().join(",")
+                    }
+                    _ => token.to_owned(),
+                }
+            }
+            _ => token.to_owned(),
+        }
+    };
+
+    calendar.split(' ')
+            .map(|part| {
+                let mut expanded = String::with_capacity(part.len());
+                let mut token = String::new();
+                for c in part.chars() {
+                    if ":-,".contains(c) {
+                        expanded.push_str(&expand(&token));
+                        expanded.push(c);
+                        token.clear();
+                    } else {
+                        token.push(c);
+                    }
+                }
+                expanded.push_str(&expand(&token));
+                expanded
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+}
+
+/// Pins a calendar expression to the given years, spelling out the named ones
+/// first: `daily` with year `2025` becomes `2025-*-* 00:00:00`.
+fn with_year(calendar: &str, year: &str) -> String {
+    let mut parts = spelled_out(calendar).split(' ').map(str::to_owned).collect::<Vec<_>>();
+    let time = parts.len() - 1;
+    if time > 0 && parts[time - 1].starts_with("*-") {
+        parts[time - 1] = format!("{}{}", year, &parts[time - 1][1..]);
+    } else {
+        // a time alone, like *:0/5, is on any date
+        parts[time] = format!("{}-*-* {}", year, parts[time]);
+    }
+    parts.join(" ")
+}
+
+/// Calendar expression of a job run on the last of its weekdays in a month, which
+/// falls in the last seven days of it: `0 9 * * 5` becomes `Fri *-*~07/1 09:00:00`.
+/// `None` unless the calendar has weekdays and no days of month.
+fn last_weekday_oncalendar(cal: &Calendar) -> Option<Vec<String>> {
+    let Calendar { ref dows, ref days, ref mons, ref hrs, ref mins } = *cal;
+
+    let dows = linearize_dows(dows);
+    if dows.is_empty() || linearize(days, "*", ToString::to_string) != "*" {
+        return None;
+    }
+
+    Some(vec![format!("{} *-{}~07/1 {}:{}:00",
+                      dows,
+                      linearize(mons, "*", |&mon| (mon as u8).to_string()),
+                      linearize(hrs, "*", |hr| format!("{:0>2}", hr.to_string())),
+                      linearize(mins, "*", |min| format!("{:0>2}", min.to_string())))])
+}
+
+/// Time between two runs of a job, as a systemd time span. For calendars this
+/// is the smallest gap between two values of the most frequently changing field.
+fn period_length(entry: &CrontabEntry, minutely_step: u64) -> Option<String> {
+    if let Some(period) = entry.period() {
+        return match *period {
+            Period::Reboot => None,
+            Period::Minutely => Some(format!("{}min", minutely_step)),
+            Period::Hourly => Some("1h".to_owned()),
+            Period::Midnight | Period::Daily => Some("1d".to_owned()),
+            Period::Weekly => Some("1w".to_owned()),
+            Period::Monthly => Some("1month".to_owned()),
+            Period::Quaterly => Some("3month".to_owned()),
+            Period::Biannually => Some("6month".to_owned()),
+            Period::Yearly => Some("1y".to_owned()),
+            Period::Days(days) => Some(format!("{}d", days)),
+        };
+    }
+
+    let cal = entry.calendar()?;
+    let numbers = |values: Vec<String>| values.iter().filter_map(|v| v.parse::<usize>().ok()).collect::<BTreeSet<_>>();
+    let mins = numbers(cal.mins.iter().flat_map(interval_values).map(|v| v.to_string()).collect());
+    let hrs = numbers(cal.hrs.iter().flat_map(interval_values).map(|v| v.to_string()).collect());
+    let days = numbers(cal.days.iter().flat_map(interval_values).map(|v| v.to_string()).collect());
+    let mons = cal.mons.iter().flat_map(interval_values).map(|mon| mon as usize).collect::<BTreeSet<_>>();
+    let dows = cal.dows.iter().flat_map(interval_values).map(|dow| dow as usize % 7).collect::<BTreeSet<_>>();
+
+    Some(if let Some(gap) = smallest_gap(&mins, 60) {
+        format!("{}min", gap)
+    } else if let Some(gap) = smallest_gap(&hrs, 24) {
+        format!("{}h", gap)
+    } else if dows.len() < 7 && days.len() == 31 {
+        smallest_gap(&dows, 7).map(|gap| format!("{}d", gap)).unwrap_or_else(|| "1w".to_owned())
+    } else if let Some(gap) = smallest_gap(&days, 31) {
+        format!("{}d", gap)
+    } else if let Some(gap) = smallest_gap(&mons, 12) {
+        format!("{}month", gap)
+    } else if mons.len() == 1 {
+        "1y".to_owned()
+    } else {
+        "1month".to_owned()
+    })
+}
+
+/// Smallest distance between two of the values of a field that wraps around
+/// after `cycle`, or `None` if there is only one value.
+fn smallest_gap(values: &BTreeSet<usize>, cycle: usize) -> Option<usize> {
+    let values = values.iter().cloned().collect::<Vec<_>>();
+    if values.len() < 2 {
+        return None;
+    }
+    values.windows(2)
+          .map(|w| w[1] - w[0])
+          .chain(Some(values[0] + cycle - values[values.len() - 1]))
+          .min()
+}
+
+/// Splits a command at cron's `%` separators: the first unescaped `%` ends the
+/// command, the following ones are the newlines of the text fed to its standard
+/// input, which gets a final newline as well. `\%` stands for a literal `%`.
+fn split_stdin(cmd: &str) -> (String, Option<String>) {
+    let mut command = String::with_capacity(cmd.len());
+    let mut stdin: Option<String> = None;
+    let mut chars = cmd.chars().peekable();
+    while let Some(c) = chars.next() {
+        let c = match c {
+            '\\' if chars.peek() == Some(&'%') => {
+                chars.next();
+                '%'
+            }
+            '%' if stdin.is_none() => {
+                stdin = Some(String::new());
+                continue;
+            }
+            '%' => '\n',
+            c => c,
+        };
+        match stdin {
+            Some(ref mut stdin) => stdin.push(c),
+            None => command.push(c),
+        }
+    }
+    if let Some(ref mut stdin) = stdin {
+        if !stdin.ends_with('\n') {
+            stdin.push('\n');
+        }
+    }
+    (command, stdin)
+}
+
+/// The here-document redirection that feeds `text`, which ends with a newline, to
+/// a command of a shell script, ended by a word none of its lines are.
+fn here_document(text: &str) -> String {
+    let mut end = "CRON_STDIN".to_owned();
+    while text.lines().any(|line| line == end) {
+        end.push('_');
+    }
+    format!(" <<'{}'\n{}{}", end, text, end)
+}
+
+/// The `SHELL_ARGS` a shell runs a script with, rather than a command: the ones
+/// before the `c` that takes the command, e.g. `-e` of `-ec`.
+fn script_flags(shell_args: &str) -> String {
+    let mut flags = shell_args.split_whitespace().map(str::to_owned).collect::<Vec<_>>();
+    if let Some(last) = flags.pop() {
+        if last.len() > 2 {
+            flags.push(last[..last.len() - 1].to_owned());
+        }
+    }
+    flags.join(" ")
+}
+
+/// Splits the leading `NAME=value` words off a command, the assignments a shell
+/// makes for that command only. Values with quotes or anything else the shell
+/// would expand stay in the command, which is then run by a script as before,
+/// and so do the assignments of pipelines, lists and redirections, which only
+/// apply to their first command. An assignment made again replaces the first.
+fn split_inline_env(cmd: &str) -> (String, Vec<(String, String)>) {
+    let mut assignments = Vec::new();
+    let mut rest = cmd.trim_start();
+    loop {
+        let word = rest.split_whitespace().next().unwrap_or("");
+        let (name, value) = match word.find('=') {
+            Some(pos) => (&word[..pos], &word[pos + 1..]),
+            None => break,
+        };
+        let is_name = name.chars().next().map(|c| c.is_ascii_alphabetic() || c == '_').unwrap_or(false) &&
+                      name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_name || value.chars().any(|c| "\\'\"$`;&|<>(){}*?[]~#".contains(c)) {
+            break;
+        }
+        assignments.retain(|assignment: &(String, String)| assignment.0 != name);
+        assignments.push((name.to_owned(), value.to_owned()));
+        rest = rest[word.len()..].trim_start();
+    }
+    // a line of assignments only has no command left to run
+    if assignments.is_empty() || rest.is_empty() || rest.contains(|c| "|;&<>\n".contains(c)) {
+        return (cmd.to_owned(), Vec::new());
+    }
+    (rest.to_owned(), assignments)
+}
+
+/// Checks shell flags are options only, the last of which makes the shell run
+/// a command string, like `-c`, `-lc` or `--login -c`.
+fn is_shell_command_flags(flags: &str) -> bool {
+    let flags = flags.split_whitespace().collect::<Vec<_>>();
+    flags.iter().all(|f| f.len() > 1 && f.starts_with('-') && f.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')) &&
+    flags.last().map(|f| !f.starts_with("--") && f.ends_with('c')).unwrap_or(false)
+}
+
+/// Quotes a command line argument for `ExecStart=`, where `%` starts a specifier
+/// and `$` a variable substitution.
+fn quote_exec_arg(arg: &str) -> String {
+    let mut quoted = String::with_capacity(arg.len() + 2);
+    quoted.push('\'');
+    for c in arg.chars() {
+        match c {
+            '\\' | '\'' => quoted.push('\\'),
+            '%' | '$' => quoted.push(c),
+            _ => (),
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Escapes a value for a double-quoted `Environment=` assignment, where `%` starts
+/// a specifier. `$` is left alone, only command lines expand variables.
+fn escape_env_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | '"' => escaped.push('\\'),
+            '%' => escaped.push('%'),
+            _ => (),
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Loose check that a value only consists of what makes up a systemd calendar
+/// expression: weekday and month names, numbers and their separators, and a time
+/// zone. Whether it actually parses is left to systemd.
+fn is_calendar_expression(value: &str) -> bool {
+    value.chars().any(|c| c.is_ascii_alphanumeric() || c == '*') &&
+    value.chars().all(|c| c.is_ascii_alphanumeric() || " *,./:~-_+".contains(c))
+}
+
+/// Checks a time zone is one of the system's, like `Europe/Berlin` or `UTC`, as
+/// systemd only takes those in calendar expressions.
+fn is_timezone(zone: &str, config: &GeneratorConfig) -> bool {
+    !zone.starts_with('/') && !zone.split('/').any(|part| part.is_empty() || part == "." || part == "..") &&
+    zone.chars().all(|c| c.is_ascii_alphanumeric() || "/_+-".contains(c)) &&
+    metadata(config.rooted("/usr/share/zoneinfo").join(zone)).map(|m| m.is_file()).unwrap_or(false)
+}
+
+/// The name systemd uses for a day of week, from its cron number (0 or 7 is
+/// Sunday) or a name of at least three letters in any case, like `sun` or `Sunday`.
+fn weekday_name(value: &str) -> Option<&'static str> {
+    static NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    static FULL_NAMES: [&str; 7] = ["sunday", "monday", "tuesday", "wednesday", "thursday", "friday", "saturday"];
+
+    let value = value.to_lowercase();
+    match value.parse::<usize>() {
+        Ok(day) if day <= 7 => Some(NAMES[day % 7]),
+        Ok(_) => None,
+        Err(_) if value.len() >= 3 => FULL_NAMES.iter().position(|name| name.starts_with(&*value)).map(|day| NAMES[day]),
+        Err(_) => None,
+    }
+}
+
+/// Checks a name is usable as a unit name, like `backup.service`.
+fn is_unit_name(name: &str) -> bool {
+    name.len() <= 255 && !name.starts_with('.') &&
+    name.chars().all(|c| c.is_ascii_alphanumeric() || ":-_.\\@".contains(c))
+}
+
+/// Checks a value is an exit code from 0 to 255 or a signal name, like `SIGHUP` or `HUP`.
+fn is_exit_status(value: &str) -> bool {
+    let name = value.trim_start_matches("SIG");
+    value.parse::<u8>().is_ok() || (!name.is_empty() && name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()))
+}
+
+/// Checks a value is a time span systemd understands, like `90`, `1h 30min` or `infinity`.
+fn is_time_span(value: &str) -> bool {
+    static UNITS: [&str; 29] = ["", "us", "usec", "ms", "msec", "s", "sec", "second", "seconds", "m", "min",
+                                "minute", "minutes", "h", "hr", "hour", "hours", "d", "day", "days", "w",
+                                "week", "weeks", "M", "month", "months", "y", "year", "years"];
+
+    if value == "infinity" {
+        return true;
+    }
+
+    let mut rest = value.trim();
+    if rest.is_empty() {
+        return false;
+    }
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+        if number_len == 0 || rest[..number_len].parse::<f64>().is_err() {
+            return false;
+        }
+        rest = rest[number_len..].trim_start();
+        let unit_len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+        if !UNITS.contains(&&rest[..unit_len]) {
+            return false;
+        }
+        rest = rest[unit_len..].trim_start();
+    }
+    true
+}
+
+/// Checks a value is a byte size systemd understands for memory limits, like
+/// `1048576`, `512M`, `1.5G` or `infinity`.
+fn is_byte_size(value: &str) -> bool {
+    if value == "infinity" {
+        return true;
+    }
+
+    let number = value.trim_end_matches(|c| "KMGTPE".contains(c));
+    value.len() - number.len() <= 1 && !number.is_empty() &&
+    number.chars().all(|c| c.is_ascii_digit() || c == '.') && number.parse::<f64>().is_ok()
+}
+
+/// Reads a boolean crontab flag: `yes`, `true` or `1` in any case turn it on,
+/// anything else (or nothing) leaves it off.
+fn env_flag(env: &BTreeMap<String, String>, name: &str) -> bool {
+    env.get(name)
+       .map(|v| matches!(&*v.trim().to_lowercase(), "yes" | "true" | "1"))
+       .unwrap_or(false)
+}
+
+/// Name of the directory the units of a crontab are grouped in, its path below
+/// the root with the slashes turned into dashes, e.g. `etc-cron.d-backup`.
+fn source_dir_name(path: &Path, config: &GeneratorConfig) -> String {
+    path.strip_prefix(&config.root)
+        .unwrap_or(path)
+        .iter()
+        .filter_map(|c| c.to_str())
+        .filter(|c| *c != "/")
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Looks up a unit of the given name installed outside of the generator directories.
+fn find_system_unit(name: &str, config: &GeneratorConfig) -> Option<PathBuf> {
+    SYSTEM_UNIT_DIRS.iter()
+                    .map(|dir| config.rooted(dir).join(name))
+                    .find(|path| metadata(path).is_ok())
+}
+
+/// Whether a unit file was written by the generator, as `--install` does, from
+/// the comment it starts with.
+fn is_generated_unit(path: &Path) -> bool {
+    let header = b"# Generated by systemd-crontab-generator ";
+    let mut head = [0; 41];
+    File::open(path).and_then(|mut file| file.read_exact(&mut head))
+                    .map(|_| head == *header)
+                    .unwrap_or(false)
+}
+
+/// Links `link` to `target`, in place of whatever an earlier `--install` left there.
+fn replace_symlink(target: &Path, link: &Path) -> io::Result<()> {
+    match remove_file(link) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => (),
+    }
+    symlink(target, link)
+}
+
+fn write_file(path: &Path, parts: &[&[u8]]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    for part in parts {
+        file.write_all(part)?;
+    }
+    Ok(())
+}
+
+fn write_unit(path: &Path, parts: &[&[u8]]) -> io::Result<()> {
+    write_file(path, &[render_unit(parts).as_bytes()])
+}
+
+/// The contents of a unit made of `parts`, with its header and long lines folded.
+fn render_unit(parts: &[&[u8]]) -> String {
+    let unit = String::from_utf8_lossy(&parts.concat()).into_owned();
+    let folded = unit.lines().map(fold_long_line).collect::<Vec<_>>().join("\n") + "\n";
+    format!("# Generated by systemd-crontab-generator {}\n{}", VERSION, folded)
+}
+
+/// Writes the `name` drop-in of a unit, returning its path relative to `dstdir`.
+fn write_drop_in(dstdir: &Path, unit_name: &str, name: &str, parts: &[&[u8]]) -> io::Result<PathBuf> {
+    let drop_in = Path::new(&format!("{}.d", unit_name)).join(name);
+    create_dir_all(dstdir.join(drop_in.parent().unwrap()))?;
+    write_unit(&dstdir.join(&drop_in), parts)?;
+    Ok(drop_in)
+}
+
+/// Splits a unit file line longer than `MAX_UNIT_LINE_LEN` with backslash continuations.
+/// systemd joins continued lines with a single space and strips leading whitespace,
+/// so lines are only split at a lone space, and never right after a backslash,
+/// which would turn the continuation into an escaped character. Nor right before
+/// a `#` or `;`, as a continuation line starting with one is dropped as a comment,
+/// and a trailing `# ...` is as much a part of a cron command as the rest of it.
+fn fold_long_line(line: &str) -> String {
+    if line.len() <= MAX_UNIT_LINE_LEN || line.starts_with('#') || line.starts_with(';') {
+        return line.to_owned();
+    }
+
+    let mut folded = String::with_capacity(line.len() + line.len() / MAX_UNIT_LINE_LEN * 2);
+    let mut rest = line;
+    while rest.len() > MAX_UNIT_LINE_LEN {
+        let splits = {
+            let bytes = rest.as_bytes();
+            rest.match_indices(' ')
+                .map(|(i, _)| i)
+                .filter(|&i| {
+                    i > 0 && i + 1 < bytes.len() && bytes[i - 1] != b' ' && bytes[i - 1] != b'\\' && bytes[i + 1] != b' ' &&
+                    bytes[i + 1] != b'#' && bytes[i + 1] != b';'
+                })
+                .collect::<Vec<_>>()
+        };
+        let split = match splits.iter().rev().find(|&&i| i <= MAX_UNIT_LINE_LEN).or_else(|| splits.first()) {
+            Some(&split) => split,
+            None => break,
+        };
+        folded.push_str(&rest[..split]);
+        folded.push_str("\\\n");
+        rest = &rest[split + 1..];
+    }
+    folded.push_str(rest);
+    folded
+}
+
+/// Best-effort check that the day-of-month and month fields intersect at all,
+/// e.g. `30 2` (February 30th) never does.
+fn calendar_can_fire(cal: &Calendar) -> bool {
+    let first_day = cal.days
+                       .iter()
+                       .flat_map(interval_values)
+                       .filter_map(|day| day.to_string().parse::<u8>().ok())
+                       .min()
+                       .unwrap_or(1);
+    let longest_month = cal.mons
+                           .iter()
+                           .flat_map(interval_values)
+                           .map(|mon| {
+                               match mon as u8 {
+                                   2 => 29,
+                                   4 | 6 | 9 | 11 => 30,
+                                   _ => 31,
+                               }
+                           })
+                           .max()
+                           .unwrap_or(31);
+    first_day <= longest_month
+}
+
+/// The values of a field interval, in order: its start, and the ones a step
+/// after another up to its end. `Interval::iter` can't be used, it adds the
+/// step with the saturation of the values, which ends `*/10` minutes with 59.
+fn interval_values<T: Limited>(interval: &Interval<T>) -> Vec<T> {
+    let (mut value, last, step) = match *interval {
+        Interval::Value(value) => (value, value, 1),
+        Interval::Range(first, last, step) => (first, last, step),
+        Interval::Full(step) => (T::min_value(), T::max_value(), step),
+    };
+    let mut values = vec![value];
+    // the value a step less one on is still below the end, so the next one isn't past it
+    while value + (step - 1) < last {
+        value = value + step;
+        values.push(value);
+    }
+    values
+}
+
+fn linearize<T, C>(input: &[Interval<T>], star: &str, conv: C) -> String
+    where T: Limited,
+          C: Fn(&T) -> String
+{
+    // a full range spelled out, like 0-23 for hours, is as good as a star, and
+    // no values at all mustn't leave a hole like *--1 in the calendar expression
+    let values = input.iter().flat_map(interval_values).collect::<BTreeSet<_>>();
+    if values.is_empty() || values == interval_values(&Interval::Full(1)).into_iter().collect() {
+        star.to_owned()
+    } else {
+        let mut output = String::new();
+        for part in values.iter() {
+            output.push_str(&conv(part));
+            output.push(',');
+        }
+        output.pop();
+        output
+    }
+}
+
+/// Like `linearize` for the day of month, with runs of three days or more written
+/// as a range, e.g. `8..14`, which `--compat` before 236 spells out again.
+fn linearize_days(input: &[Interval<Day>]) -> String {
+    let days = linearize(input, "*", ToString::to_string);
+    if days == "*" {
+        return days;
+    }
+
+    let mut runs: Vec<(u32, u32)> = Vec::new();
+    for day in days.split(',').filter_map(|day| day.parse::<u32>().ok()) {
+        match runs.last_mut() {
+            Some(&mut (_, ref mut last)) if *last + 1 == day => *last = day,
+            _ => runs.push((day, day)),
+        }
+    }
+    runs.iter()
+        .map(|&(first, last)| {
+            match last - first {
+                0 => first.to_string(),
+                1 => format!("{},{}", first, last),
+                _ => format!("{}..{}", first, last),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Like `linearize` for the day of week, with the names systemd uses and runs of
+/// three days or more written as a range, e.g. `Mon..Fri`.
+fn linearize_dows(input: &[Interval<DayOfWeek>]) -> String {
+    static NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for day in input.iter().flat_map(interval_values).map(|dow| dow as usize % 7).collect::<BTreeSet<_>>() {
+        match runs.last_mut() {
+            Some(&mut (_, ref mut last)) if *last + 1 == day => *last = day,
+            _ => runs.push((day, day)),
+        }
+    }
+    if runs == [(0, 6)] {
+        // every day, which systemd expresses by leaving the weekday out
+        return String::new();
+    }
+
+    // a run into Sunday continues the one up to Saturday, so the weekend is Sat,Sun,
+    // spelled out as systemd ranges don't wrap around
+    if runs.len() > 1 && runs[0].0 == 0 && runs[runs.len() - 1].1 == 6 {
+        let (_, last) = runs.remove(0);
+        runs.last_mut().unwrap().1 = last + 7;
+    }
+
+    runs.iter()
+        .map(|&(first, last)| {
+            match last - first {
+                // Sunday ends the week in systemd, a range can't start with it
+                2 if first == 0 => "Sun,Mon,Tue".to_owned(),
+                _ if first == 0 && (3..7).contains(&last) => format!("Sun,Mon..{}", NAMES[last]),
+                0 => NAMES[first].to_owned(),
+                _ if last >= 7 => (first..last + 1).map(|day| NAMES[day % 7]).collect::<Vec<_>>().join(","),
+                1 => format!("{},{}", NAMES[first], NAMES[last]),
+                _ => format!("{}..{}", NAMES[first], NAMES[last]),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
 fn tohex(input: &[u8]) -> String {
     #[inline]
     fn hex(d: u8) -> char {
         match d {
-            0...9 => (d + 0x30) as char,
-            10...15 => (d + 0x57) as char,
+            0..=9 => (d + 0x30) as char,
+            10..=15 => (d + 0x57) as char,
             _ => unreachable!("unexpected value: {}", d),
         }
     }
 
     let mut buf = String::with_capacity(32);
-    for b in input.into_iter() {
+    for b in input.iter() {
         buf.push(hex(b >> 4));
         buf.push(hex(b & 0xf));
     }
     buf
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::env;
+    use std::fs::{File, create_dir_all, read_link, remove_dir_all};
+    use std::io::Write;
+    use std::path::Path;
+    use std::process::Command;
+    use std::str::FromStr;
+
+    use cronparse::CrontabFileError;
+    use cronparse::schedule::{Calendar, Day, Period};
+    use cronparse::crontab::{AnacrontabEntry, CrontabEntry, SystemCrontabEntry, UserCrontabEntry};
+    use rustc_serialize::base64::FromBase64;
+    use process::{CrontabKind, GeneratorConfig, parse_and_generate, process_crontab_file, process_crontab_str,
+                  resolve_dependencies};
+
+    use super::{MAX_UNIT_LINE_LEN, STRICT_HARDENING, VERSION, calendar_to_oncalendar, expand_ranges, fold_long_line,
+                hourly_within, linearize, period_to_oncalendar, schedule_to_oncalendar, write_file};
+
+    /// The units generated for a user crontab with a single job, service first.
+    fn units(crontab: &str) -> (String, String) {
+        let units = parse_and_generate(crontab, CrontabKind::User).unwrap();
+        let unit = |suffix: &str| {
+            units.iter().find(|(name, _)| name.ends_with(suffix)).map(|(_, unit)| unit.clone()).unwrap_or_default()
+        };
+        (unit(".service"), unit(".timer"))
+    }
+
+    #[test]
+    fn jobs_can_name_jobs_further_down() {
+        let units = parse_and_generate("REQUIRES=fetch\n0 5 * * * /bin/report\nREQUIRES=\n\
+                                        NAME=fetch\n0 4 * * * /bin/fetch\n", CrontabKind::User).unwrap();
+        let fetch = units.iter()
+                         .find(|(name, unit)| name.ends_with(".service") && unit.contains("/bin/fetch"))
+                         .map(|(name, _)| name.clone())
+                         .unwrap();
+        let dependencies = unit(&units, "dependencies.conf");
+        assert!(dependencies.contains(&format!("Requires={}\n", fetch)), "{}", dependencies);
+        assert!(dependencies.contains(&format!("After={}\n", fetch)), "{}", dependencies);
+        assert_eq!(units.iter().filter(|(name, _)| name.ends_with("dependencies.conf")).count(), 1);
+    }
+
+    #[test]
+    fn mutual_conflicts_are_on_both_jobs() {
+        let units = parse_and_generate("NAME=backup-a\nCONFLICTS=backup-b\n0 1 * * * /bin/a\n\
+                                        NAME=backup-b\nCONFLICTS=backup-a\n0 2 * * * /bin/b\n", CrontabKind::User).unwrap();
+        let service = |command: &str| {
+            units.iter().find(|(name, unit)| name.ends_with(".service") && unit.contains(command)).map(|(name, _)| name.clone()).unwrap()
+        };
+        let (a, b) = (service("/bin/a"), service("/bin/b"));
+        assert_eq!(unit(&units, &format!("{}.d/dependencies.conf", a)).lines().last(), Some(&*format!("Conflicts={}", b)));
+        assert_eq!(unit(&units, &format!("{}.d/dependencies.conf", b)).lines().last(), Some(&*format!("Conflicts={}", a)));
+    }
+
+    #[test]
+    fn unknown_job_names_are_warned_about() {
+        let config = GeneratorConfig { owner: Some(0), ..GeneratorConfig::new("-").dry_run(true) };
+        let mut summary = process_crontab_str::<UserCrontabEntry, _>("AFTER=nothing\n0 5 * * * /bin/true\n", "-", &config);
+        resolve_dependencies(&mut summary, &config);
+        assert!(summary.warnings.iter().any(|w| w.message == "ignoring AFTER, no job is named \"nothing\""),
+                "{:?}", summary.warnings);
+        assert!(summary.jobs[0].units.iter().all(|(name, _)| !name.ends_with("dependencies.conf")));
+    }
+
+    #[test]
+    fn delays_stop_at_the_end_of_the_day() {
+        let (_, timer) = units("DELAY=90\n@daily /bin/true\n");
+        assert!(timer.contains("OnCalendar=*-*-* 01:30:00\n"), "{}", timer);
+
+        let config = GeneratorConfig { owner: Some(0), ..GeneratorConfig::new("-").dry_run(true) };
+        let summary = process_crontab_str::<AnacrontabEntry, _>("START_HOURS_RANGE=20-22\n7 300 late /bin/true\n\
+                                                                  1 90 early /bin/true\n", "-", &config);
+        let timers = summary.jobs.iter().map(|job| unit(&job.units, ".timer")).collect::<Vec<_>>();
+        assert!(timers[0].contains("OnCalendar=Mon *-*-* 23:59:00\n"), "{}", timers[0]);
+        assert!(timers[1].contains("OnCalendar=*-*-* 21:30:00\n"), "{}", timers[1]);
+        assert_eq!(summary.warnings.len(), 1, "{:?}", summary.warnings);
+        assert!(summary.warnings[0].message.ends_with("goes past the end of the day, the job starts at 23:59 instead"));
+
+        let (_, timer) = units("DELAY=75\n@hourly /bin/true\n");
+        assert!(timer.contains("OnCalendar=*-*-* *:59:00\n"), "{}", timer);
+    }
+
+    #[test]
+    fn mail_command_is_passed_on() {
+        let (service, _) = units("ALLOWED_ENV=PATH\nMAIL_COMMAND=/usr/bin/msmtp -a backup\n0 5 * * * /bin/true\n");
+        assert!(service.contains("Environment=\"MAIL_COMMAND=/usr/bin/msmtp -a backup\"\n"), "{}", service);
+    }
+
+    #[test]
+    fn mail_goes_to_every_recipient() {
+        let (service, _) = units("MAILTO=alice,bob\n0 5 * * * /bin/true\n");
+        assert!(service.contains("OnFailure=cron-failure@%i.service\n"), "{}", service);
+        assert!(service.contains("Environment=\"MAILTO=alice,bob\"\n"), "{}", service);
+
+        let (service, _) = units("MAILTO=\n0 5 * * * /bin/true\n");
+        assert!(!service.contains("OnFailure="), "{}", service);
+        let (service, _) = units("MAILTO=\"  \"\n0 5 * * * /bin/true\n");
+        assert!(!service.contains("OnFailure="), "{}", service);
+
+        let config = GeneratorConfig { owner: Some(0), ..GeneratorConfig::new("-").dry_run(true) };
+        let summary = process_crontab_str::<UserCrontabEntry, _>("MAILTO=alice,,b!ob\n0 5 * * * /bin/true\n", "-", &config);
+        let warnings = summary.warnings.iter().map(|w| &*w.message).collect::<Vec<_>>();
+        assert_eq!(warnings, ["empty recipient in MAILTO: \"alice,,b!ob\"",
+                              "MAILTO recipient doesn't look like an address or user name: \"b!ob\""]);
+    }
+
+    #[test]
+    fn long_anacrontab_periods_run_by_months_up_to_a_year() {
+        let config = GeneratorConfig { owner: Some(0), ..GeneratorConfig::new("-").dry_run(true) };
+        let summary = process_crontab_str::<AnacrontabEntry, _>("90 0 quarter /bin/true\n365 0 year /bin/yearly\n\
+                                                                  400 0 long /bin/long\n", "-", &config);
+        let timers = summary.jobs.iter().map(|job| unit(&job.units, ".timer")).collect::<Vec<_>>();
+        assert!(timers[0].contains("OnCalendar=*-1/3-1 00:00:00\n"), "{}", timers[0]);
+        assert!(timers[1].contains("OnCalendar=yearly\n"), "{}", timers[1]);
+        assert!(timers[2].contains("OnCalendar=yearly\n"), "{}", timers[2]);
+        let warnings = summary.warnings.iter().map(|w| (w.lineno, &*w.message)).collect::<Vec<_>>();
+        assert_eq!(warnings, [(Some(3), "anacrontab period of 400 days is rounded to a year")]);
+    }
+
+    #[test]
+    fn percent_signs_feed_the_standard_input() {
+        let (service, _) = units("0 5 * * * mail root%Subject: hi%body\n");
+        assert!(service.contains("StandardInput=data\n"), "{}", service);
+        let data = service.lines().find(|line| line.starts_with("StandardInputData=")).unwrap();
+        assert_eq!(data["StandardInputData=".len()..].from_base64().unwrap(), b"Subject: hi\nbody\n");
+
+        // systemd before 236 has no StandardInputData=, the script of the command feeds it
+        let config = GeneratorConfig { owner: Some(0), ..GeneratorConfig::new("-").dry_run(true).compat(Some(235)) };
+        let summary = process_crontab_str::<UserCrontabEntry, _>("0 5 * * * mail root%Subject: hi%body\n\
+                                                                  SHELL_ARGS=-ec\n0 6 * * * mail root%CRON_STDIN\n",
+                                                                 "-", &config);
+        let service = unit(&summary.jobs[0].units, ".service");
+        assert!(!service.contains("StandardInput"), "{}", service);
+        assert_eq!(unit(&summary.jobs[0].units, ".sh"), "#!/bin/sh\nmail root <<'CRON_STDIN'\nSubject: hi\nbody\nCRON_STDIN\n");
+        let service = unit(&summary.jobs[1].units, ".service");
+        assert!(service.contains("\nExecStart=/bin/sh -e -/cron-"), "{}", service);
+        assert_eq!(unit(&summary.jobs[1].units, ".sh"), "#!/bin/sh\nmail root <<'CRON_STDIN_'\nCRON_STDIN\nCRON_STDIN_\n");
+    }
+
+    #[test]
+    fn leading_assignments_of_a_simple_command_go_into_its_environment() {
+        let (service, _) = units("LANG=en_US.UTF-8\n0 5 * * * FOO=bar LANG=C LANG=POSIX /usr/bin/job\n");
+        let environment = service.lines().filter(|l| l.starts_with("Environment=")).collect::<Vec<_>>();
+        assert_eq!(environment, ["Environment=\"FOO=bar\"", "Environment=\"LANG=POSIX\""]);
+
+        // the other commands of a pipeline or list don't get them
+        for command in ["LANG=C sort /etc/passwd | uniq > /tmp/x", "LANG=C date; date", "LANG=C date && date",
+                        "LANG=C date >/tmp/x", "LANG=C sleep 1 &"].iter() {
+            let generated = parse_and_generate(&format!("0 5 * * * {}\n", command), CrontabKind::User).unwrap();
+            assert_eq!(unit(&generated, ".sh"), format!("#!/bin/sh\n{}\n", command));
+            let service = unit(&generated, ".service");
+            assert!(!service.contains("Environment="), "{}", service);
+        }
+    }
+
+    #[test]
+    fn control_variables_are_not_passed_on() {
+        let (service, _) = units("DESCRIPTION=backup\nHARDENING=strict\nALLOWED_ENV=FOO\nFOO=bar\nMAILTO=root\n\
+                                  0 5 * * * /bin/true\n");
+        assert!(service.contains("Environment=\"FOO=bar\""), "{}", service);
+        assert!(service.contains("Environment=\"MAILTO=root\""), "{}", service);
+        for name in ["DESCRIPTION", "HARDENING", "ALLOWED_ENV"].iter() {
+            assert!(!service.contains(&format!("Environment=\"{}=", name)), "{}", service);
+        }
+    }
+
+    #[test]
+    fn stepped_fields_end_at_their_last_step() {
+        let calendar = |schedule: &str| calendar_to_oncalendar(&schedule.parse::<Calendar>().unwrap()).unwrap();
+        assert_eq!(calendar("*/10 * * * *"), ["*:0/10"]);
+        assert_eq!(calendar("*/15 * * * *"), ["*:0/15"]);
+        assert_eq!(calendar("5-59/20 * * * *"), ["*:5/20"]);
+        assert_eq!(calendar("0 */6 * * *"), ["*-*-* 00,06,12,18:00:00"]);
+        assert_eq!(calendar("0 0 */10 * *"), ["*-*-1,11,21,31 00:00:00"]);
+        assert_eq!(calendar("0 0 1 */5 *"), ["*-1,6,11-1 00:00:00"]);
+    }
+
+    #[test]
+    fn steps_that_dont_divide_the_range_add_no_last_value() {
+        let calendar = |schedule: &str| calendar_to_oncalendar(&schedule.parse::<Calendar>().unwrap()).unwrap();
+        assert_eq!(calendar("*/7 * * * *"), ["*:0/7"]);
+        assert_eq!(calendar("*/7 5 * * *"), ["*-*-* 05:00,07,14,21,28,35,42,49,56:00"]);
+        assert_eq!(calendar("0 */5 * * *"), ["*-*-* 00,05,10,15,20:00:00"]);
+        // listing every value is as good as a star, stepped or not
+        assert_eq!(calendar("0 0 * * */1"), ["daily"]);
+        assert_eq!(calendar("0 0 * * 0-6"), ["daily"]);
+        assert_eq!(calendar("0 0 * * */4"), ["Sun,Thu *-*-* 00:00:00"]);
+    }
+
+    #[test]
+    fn weekday_runs_from_sunday_are_valid_ranges() {
+        let calendar = |schedule: &str| calendar_to_oncalendar(&schedule.parse::<Calendar>().unwrap()).unwrap();
+        assert_eq!(calendar("0 0 * * 0-3"), ["Sun,Mon..Wed *-*-* 00:00:00"]);
+        assert_eq!(calendar("0 0 * * 0-2"), ["Sun,Mon,Tue *-*-* 00:00:00"]);
+        assert_eq!(calendar("0 0 * * 0,1"), ["Sun,Mon *-*-* 00:00:00"]);
+        assert_eq!(calendar("0 0 * * 5-6,0"), ["Fri,Sat,Sun *-*-* 00:00:00"]);
+    }
+
+    #[test]
+    fn shutdown_jobs_only_come_from_shutdown_lines() {
+        let (service, _) = units("@shutdown /bin/true\n");
+        assert!(service.contains("Before=shutdown.target"), "{}", service);
+        assert!(service.contains("DefaultDependencies=no"), "{}", service);
+        assert!(!service.contains("Environment="), "{}", service);
+
+        // a variable can't make a @reboot job one
+        let (service, _) = units("AT_SHUTDOWN=yes\n@reboot /bin/true\n");
+        assert!(!service.contains("Before=shutdown.target"), "{}", service);
+    }
+
+    /// Xorshift, enough to draw schedules from and reproducible from its seed.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0 % n
+        }
+
+        fn between(&mut self, min: u64, max: u64) -> u64 {
+            min + self.below(max - min + 1)
+        }
+    }
+
+    /// A cron field of values, ranges and steps between `min` and `max`, or `*`.
+    fn random_field(rng: &mut Rng, min: u64, max: u64) -> String {
+        if rng.below(3) == 0 {
+            return "*".to_owned();
+        }
+        (0..rng.between(1, 3)).map(|_| {
+                                  let (first, second) = (rng.between(min, max), rng.between(min, max));
+                                  let (first, last) = if first <= second { (first, second) } else { (second, first) };
+                                  match rng.below(4) {
+                                      0 => first.to_string(),
+                                      1 => format!("{}-{}", first, last),
+                                      2 => format!("{}-{}/{}", first, last, rng.between(1, max - min + 1)),
+                                      _ => format!("*/{}", rng.between(1, max - min + 1)),
+                                  }
+                              })
+                              .collect::<Vec<_>>()
+                              .join(",")
+    }
+
+    /// The values a field of `random_field` stands for.
+    fn field_values(field: &str, min: u64, max: u64) -> BTreeSet<u64> {
+        field.split(',')
+             .flat_map(|part| {
+                 let mut halves = part.splitn(2, '/');
+                 let range = halves.next().unwrap();
+                 let step = halves.next().map_or(1, |step| step.parse::<usize>().unwrap());
+                 let (first, last) = match range.split_once('-') {
+                     _ if range == "*" => (min, max),
+                     Some((first, last)) => (first.parse().unwrap(), last.parse().unwrap()),
+                     None => (range.parse().unwrap(), range.parse().unwrap()),
+                 };
+                 (first..=last).step_by(step).collect::<Vec<_>>()
+             })
+             .collect()
+    }
+
+    /// The first `count` times a schedule runs at after the start of 2025, as cron
+    /// runs it, up to the end of 2028, like `2025-01-01 00:10:00`.
+    fn cron_elapses(schedule: &str, count: usize) -> Vec<String> {
+        let fields = schedule.split(' ').collect::<Vec<_>>();
+        let (mins, hrs) = (field_values(fields[0], 0, 59), field_values(fields[1], 0, 23));
+        let (days, mons, dows) = (field_values(fields[2], 1, 31), field_values(fields[3], 1, 12), field_values(fields[4], 0, 6));
+
+        let mut elapses = Vec::new();
+        // 2025-01-01 is a Wednesday
+        let (mut year, mut mon, mut day, mut dow) = (2025, 1, 1, 3);
+        while year < 2029 && elapses.len() < count {
+            if mons.contains(&mon) && days.contains(&day) && dows.contains(&dow) {
+                for hr in hrs.iter() {
+                    // systemd counts from right after its base time
+                    for min in mins.iter().filter(|&&min| (year, mon, day, *hr, min) != (2025, 1, 1, 0, 0)) {
+                        elapses.push(format!("{}-{:02}-{:02} {:02}:{:02}:00", year, mon, day, hr, min));
+                    }
+                }
+            }
+            let length = match mon {
+                2 if year % 4 == 0 => 29,
+                2 => 28,
+                4 | 6 | 9 | 11 => 30,
+                _ => 31,
+            };
+            dow = (dow + 1) % 7;
+            day += 1;
+            if day > length {
+                day = 1;
+                mon += 1;
+            }
+            if mon > 12 {
+                mon = 1;
+                year += 1;
+            }
+        }
+        elapses.truncate(count);
+        elapses
+    }
+
+    /// The first `count` elapses of each expression after the start of 2025, in
+    /// UTC, as systemd-analyze computes them, up to the end of 2028. `None` if it
+    /// doesn't take all the expressions.
+    fn systemd_elapses(expressions: &[&str], count: usize) -> Option<Vec<Vec<String>>> {
+        let output = Command::new("systemd-analyze")
+                         .env("TZ", "UTC")
+                         .arg("calendar")
+                         .arg(format!("--iterations={}", count))
+                         .arg("--base-time=2025-01-01 00:00:00 UTC")
+                         .args(expressions)
+                         .output()
+                         .unwrap();
+        if !output.status.success() {
+            return None;
+        }
+        // e.g. "Next elapse: Wed 2025-01-01 00:10:00 UTC", "Iter. #2: ..." or "Next elapse: never"
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        Some(stdout.split("Normalized form:")
+                   .skip(1)
+                   .map(|block| {
+                       block.lines()
+                            .map(str::trim)
+                            .filter(|l| l.starts_with("Next elapse:") || l.starts_with("Iter. #"))
+                            .filter_map(|l| {
+                                let words = l.split_once(": ").map_or("", |(_, elapse)| elapse).split(' ').collect::<Vec<_>>();
+                                if words.len() < 3 { None } else { Some(format!("{} {}", words[1], words[2])) }
+                            })
+                            .filter(|elapse| &**elapse < "2029-01-01")
+                            .collect()
+                   })
+                   .collect())
+    }
+
+    /// Checks the calendars of lots of random schedules, the spelled out ranges of
+    /// --compat included, elapse exactly when cron would run the schedules, and
+    /// that the calendars of periods are taken by systemd. Only one of the day
+    /// fields is set, the timer would run on the days both match, cron on either.
+    #[test]
+    fn random_calendars_elapse_when_cron_runs() {
+        // without systemd-analyze, or one too old for --base-time, there's nothing to check against
+        let analyze = Command::new("systemd-analyze").args(["calendar", "--base-time=2025-01-01 00:00:00 UTC", "daily"]).output();
+        if analyze.map(|out| !out.status.success()).unwrap_or(true) {
+            return;
+        }
+
+        const COUNT: usize = 12;
+        let mut rng = Rng(0x5eed_cafe_f00d);
+        let mut cases = Vec::new();
+        while cases.len() < 400 {
+            let (mut days, mut dows) = (random_field(&mut rng, 1, 31), random_field(&mut rng, 0, 6));
+            if rng.below(2) == 0 {
+                days = "*".to_owned();
+            } else {
+                dows = "*".to_owned();
+            }
+            let schedule = format!("{} {} {} {} {}",
+                                   random_field(&mut rng, 0, 59),
+                                   random_field(&mut rng, 0, 23),
+                                   days,
+                                   random_field(&mut rng, 1, 12),
+                                   dows);
+            let calendar = schedule.parse::<Calendar>().unwrap_or_else(|err| panic!("{}: {:?}", schedule, err));
+            for expression in calendar_to_oncalendar(&calendar).unwrap() {
+                cases.push((schedule.clone(), expand_ranges(&expression)));
+                cases.push((schedule.clone(), expression));
+            }
+        }
+        for chunk in cases.chunks(50) {
+            let expressions = chunk.iter().map(|(_, expression)| &**expression).collect::<Vec<_>>();
+            let elapses = systemd_elapses(&expressions, COUNT).unwrap_or_else(|| {
+                let (schedule, expression) = chunk.iter().find(|(_, expression)| systemd_elapses(&[expression], 1).is_none()).unwrap();
+                panic!("\"{}\" is written as \"{}\", which systemd doesn't take", schedule, expression)
+            });
+            for ((schedule, expression), elapses) in chunk.iter().zip(elapses) {
+                assert_eq!(elapses, cron_elapses(schedule, COUNT), "\"{}\" is written as \"{}\"", schedule, expression);
+            }
+        }
+
+        // the periods, at any start hour and delay, and anacron's periods in days
+        let mut expressions = Vec::new();
+        for _ in 0..100 {
+            let (delay, hour) = (rng.below(60), rng.below(24));
+            let periods = [Period::Minutely, Period::Hourly, Period::Midnight, Period::Daily, Period::Weekly,
+                           Period::Monthly, Period::Quaterly, Period::Biannually, Period::Yearly,
+                           Period::Days(rng.between(1, 400) as u16)];
+            for period in periods.iter() {
+                let schedule = format!("{:?} at {:02}:{:02}", period, hour, delay);
+                expressions.extend(period_to_oncalendar(period, delay, hour).unwrap().into_iter().map(|e| (schedule.clone(), e)));
+            }
+            let end = rng.below(24);
+            expressions.push((format!("hourly from {} to {}", hour, end), hourly_within(hour, end, delay)));
+        }
+        for (schedule, expression) in expressions.iter() {
+            assert!(systemd_elapses(&[expression], 1).is_some(), "\"{}\" is written as \"{}\", which systemd doesn't take",
+                    schedule, expression);
+        }
+    }
+
+    #[test]
+    fn drop_ins_go_into_the_installed_units_named_by_the_job() {
+        let root = ::std::env::temp_dir().join(format!("systemd-crontab-generator-drop-in-{}", ::std::process::id()));
+        let _ = ::std::fs::remove_dir_all(&root);
+        create_dir_all(root.join("etc/systemd/system")).unwrap();
+        write_file(&root.join("etc/passwd"), &[b"root:x:0:0:root:/root:/bin/sh\n"]).unwrap();
+        write_file(&root.join("etc/systemd/system/backup.service"), &[b"[Service]\nExecStart=/usr/bin/backup\n"]).unwrap();
+        write_file(&root.join("etc/systemd/system/backup.timer"), &[b"[Timer]\nOnCalendar=daily\n"]).unwrap();
+
+        let config = GeneratorConfig { owner: Some(0), ..GeneratorConfig::new(root.join("run/systemd/generator")).root(&root) };
+        let crontab = "DROP_IN=yes\nNAME=backup\n30 2 * * * root /usr/bin/backup --all\n";
+        let summary = process_crontab_str::<SystemCrontabEntry, _>(crontab, "-", &config);
+        let read = |file: &str| ::std::fs::read_to_string(root.join("run/systemd/generator").join(file)).unwrap_or_default();
+
+        // the service's command is reset before it's given the job's
+        let service = read("backup.service.d/override.conf");
+        assert!(service.contains("\n[Service]\nExecStart=\n"), "{}", service);
+        assert!(service.contains("\nExecStart=/run/systemd/generator/cron-"), "{}", service);
+        let timer = read("backup.timer.d/override.conf");
+        assert!(timer.contains("\n[Timer]\nOnCalendar=\n"), "{}", timer);
+        assert!(timer.contains("\nOnCalendar=*-*-* 02:30:00\n"), "{}", timer);
+        // no full units of their own, only the script of the command
+        let mut files = summary.jobs[0].files.iter()
+                                            .map(|file| file.to_str().unwrap())
+                                            .filter(|file| !file.ends_with(".sh"))
+                                            .collect::<Vec<_>>();
+        files.sort();
+        assert_eq!(files, ["backup.service.d/override.conf", "backup.timer.d/override.conf", "cron.target.wants/backup.timer"]);
+
+        let _ = ::std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn path_conditions_need_absolute_paths() {
+        let (service, _) = units("REQUIRE_PATH=/mnt/backup\nASSERT_PATH=!/run/nologin\n0 5 * * * /bin/true\n");
+        assert!(service.contains("\nConditionPathExists=/mnt/backup\n"), "{}", service);
+        assert!(service.contains("\nAssertPathExists=!/run/nologin\n"), "{}", service);
+
+        let config = GeneratorConfig { owner: Some(0), ..GeneratorConfig::new("-").dry_run(true) };
+        let summary = process_crontab_str::<UserCrontabEntry, _>("REQUIRE_PATH=mnt/backup\nASSERT_PATH=!!/x\n0 5 * * * /bin/true\n",
+                                                                  "-",
+                                                                  &config);
+        let service = unit(&summary.jobs[0].units, ".service");
+        assert!(!service.contains("PathExists="), "{}", service);
+        let warnings = summary.warnings.iter().map(|w| w.message.clone()).collect::<Vec<_>>();
+        assert_eq!(warnings,
+                   ["ignoring REQUIRE_PATH, expected an absolute path: \"mnt/backup\"",
+                    "ignoring ASSERT_PATH, expected an absolute path: \"!!/x\""]);
+    }
+
+    /// The units generated for `crontab`, by name, and the warnings about it.
+    fn translate<T: FromStr + 'static>(crontab: &str) -> (Vec<(String, String)>, Vec<String>)
+        where CrontabEntry: From<T>,
+              CrontabFileError: From<<T as FromStr>::Err>
+    {
+        translate_with::<T>(crontab, GeneratorConfig::new("-"))
+    }
+
+    /// Like `translate`, with the settings of `config` other than its output directory.
+    fn translate_with<T: FromStr + 'static>(crontab: &str, config: GeneratorConfig) -> (Vec<(String, String)>, Vec<String>)
+        where CrontabEntry: From<T>,
+              CrontabFileError: From<<T as FromStr>::Err>
+    {
+        let config = GeneratorConfig { owner: Some(0), ..config.dry_run(true) };
+        let summary = process_crontab_str::<T, _>(crontab, "-", &config);
+        let warnings = summary.warnings.into_iter().map(|warning| warning.message).collect();
+        (summary.jobs.into_iter().flat_map(|job| job.units).collect(), warnings)
+    }
+
+    /// The contents of the generated unit, or drop-in, whose name ends with `suffix`.
+    fn unit(units: &[(String, String)], suffix: &str) -> String {
+        units.iter().find(|(name, _)| name.ends_with(suffix)).map(|(_, unit)| unit.clone()).unwrap_or_default()
+    }
+
+    #[test]
+    fn days_no_month_has_are_warned_about() {
+        let (_, warnings) = translate::<UserCrontabEntry>("0 0 30 2 * /bin/true\n");
+        assert!(warnings.iter().any(|warning| warning.contains("will never run, no month matches its days")), "{:?}", warnings);
+
+        let (_, warnings) = translate::<UserCrontabEntry>("0 0 29 2 * /bin/true\n");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn flags_take_any_case_and_padding() {
+        for value in &["True", "YES", " yes "] {
+            let (files, _) = translate::<UserCrontabEntry>(&format!("BATCH={}\n0 5 * * * /bin/true\n", value));
+            let service = unit(&files, ".service");
+            assert!(service.contains("CPUSchedulingPolicy=idle\n"), "{}", service);
+        }
+        let (files, _) = translate::<UserCrontabEntry>("BATCH=no\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("CPUSchedulingPolicy="));
+    }
+
+    /// Joins the lines of a unit continued with a backslash, the way systemd reads them.
+    fn unfold(unit: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        let mut continued: Option<String> = None;
+        for line in unit.lines() {
+            let mut joined = match continued.take() {
+                Some(start) => start + line.trim_start(),
+                None => line.to_owned(),
+            };
+            if !joined.starts_with('#') && joined.ends_with('\\') {
+                joined.pop();
+                joined.push(' ');
+                continued = Some(joined);
+            } else {
+                lines.push(joined);
+            }
+        }
+        lines
+    }
+
+    #[test]
+    fn long_lines_are_folded_and_join_back() {
+        // escaped spaces that would stop being escaped at the end of a continued line
+        let escaped = (0..400).map(|i| format!("a\\ {}", i)).collect::<Vec<_>>().join(" ");
+        assert!(escaped.len() > 2048);
+        let line = format!("ExecStart=/bin/echo {}", escaped);
+        let folded = fold_long_line(&line);
+        assert!(folded.lines().count() > 2, "{}", folded);
+        assert!(folded.lines().all(|l| l.len() <= MAX_UNIT_LINE_LEN + 1), "{}", folded);
+        assert_eq!(unfold(&folded), [line]);
+
+        let words = (0..400).map(|i| format!("word{}", i)).collect::<Vec<_>>().join(" ");
+        let (files, _) = translate::<UserCrontabEntry>(&format!("WORDS={}\n0 5 * * * /bin/true\n", words));
+        let service = unit(&files, ".service");
+        assert!(service.lines().all(|l| l.len() <= MAX_UNIT_LINE_LEN + 1), "{}", service);
+        assert!(unfold(&service).contains(&format!("Environment=\"WORDS={}\"", words)), "{}", service);
+
+        // systemd itself is the judge of the continuations where it's installed
+        if Command::new("systemd-analyze").arg("--version").output().map(|out| !out.status.success()).unwrap_or(true) {
+            return;
+        }
+        let dir = env::temp_dir().join(format!("systemd-crontab-generator-fold-{}", ::std::process::id()));
+        create_dir_all(&dir).unwrap();
+        let path = dir.join("cron-fold.service");
+        // the crontab of the translation is gone by now, it can't be the source of the unit
+        let service = service.lines().filter(|l| !l.starts_with("SourcePath=")).collect::<Vec<_>>().join("\n");
+        write_file(&path, &[service.as_bytes()]).unwrap();
+        let output = Command::new("systemd-analyze").arg("verify").arg("--man=no").arg(&path).output().unwrap();
+        let _ = remove_dir_all(&dir);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success() && stderr.is_empty(), "{}", stderr);
+    }
+
+    #[test]
+    fn boot_jobs_can_wait_for_a_target() {
+        let (files, _) = translate::<UserCrontabEntry>("AFTER_TARGET=network-online.target\n@reboot /bin/true\n");
+        let (service, timer) = (unit(&files, ".service"), unit(&files, ".timer"));
+        assert!(service.contains("Wants=network-online.target\nAfter=network-online.target\n"), "{}", service);
+        assert!(timer.contains("After=network-online.target\n"), "{}", timer);
+        assert!(timer.contains("OnActiveSec=") && !timer.contains("OnBootSec="), "{}", timer);
+
+        let (files, _) = translate::<UserCrontabEntry>("AFTER_TARGET=network-online.target\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("\nAfter=network-online.target\n"));
+    }
+
+    #[test]
+    fn trailing_comments_are_part_of_the_command() {
+        let (files, _) = translate::<UserCrontabEntry>("0 5 * * * echo hi # not a comment\n");
+        assert_eq!(unit(&files, ".sh"), "#!/bin/sh\necho hi # not a comment\n");
+
+        // a continuation line starting with `#` or `;` would be dropped as a comment
+        let line = format!("ExecStart=/bin/echo {}", (0..600).map(|i| match i % 3 {
+                                                                0 => format!("#{}", i),
+                                                                1 => format!(";{}", i),
+                                                                _ => format!("word{}", i),
+                                                            })
+                                                            .collect::<Vec<_>>()
+                                                            .join(" "));
+        let folded = fold_long_line(&line);
+        assert!(folded.lines().count() > 2, "{}", folded);
+        assert!(folded.lines().skip(1).all(|l| !l.starts_with('#') && !l.starts_with(';')), "{}", folded);
+        assert_eq!(unfold(&folded), [line]);
+    }
+
+    #[test]
+    fn jobs_can_be_kept_out_of_home_directories() {
+        let (files, warnings) = translate::<UserCrontabEntry>("PROTECT_HOME=read-only\nREAD_WRITE_PATHS=/var/backup,-/srv/cache tmp\n\
+                                                               0 5 * * * /bin/true\n");
+        let service = unit(&files, ".service");
+        assert!(service.contains("ProtectHome=read-only\n"), "{}", service);
+        assert!(service.contains("ReadWritePaths=/var/backup -/srv/cache\n"), "{}", service);
+        assert!(warnings.iter().any(|warning| warning.contains("READ_WRITE_PATHS") && warning.contains("\"tmp\"")), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("PROTECT_HOME=somewhat\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("ProtectHome="));
+        assert!(warnings.iter().any(|warning| warning.contains("PROTECT_HOME")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn schedules_translate_to_calendar_expressions() {
+        let oncalendar = |schedule: &str, delay: u64, hour: u64| schedule_to_oncalendar(&schedule.parse().unwrap(), delay, hour);
+        assert_eq!(oncalendar("@daily", 0, 0), Some(vec!["daily".to_owned()]));
+        assert_eq!(oncalendar("@daily", 5, 6), Some(vec!["*-*-* 06:05:00".to_owned()]));
+        assert_eq!(oncalendar("0 9,17 * * 1-5", 0, 0), Some(vec!["Mon..Fri *-*-* 09,17:00:00".to_owned()]));
+        assert_eq!(oncalendar("*/10 * * * *", 0, 0), Some(vec!["*:0/10".to_owned()]));
+        assert_eq!(oncalendar("@reboot", 0, 0), None);
+    }
+
+    #[test]
+    fn identical_jobs_get_units_of_their_own() {
+        let (files, warnings) = translate::<UserCrontabEntry>("0 5 * * * /bin/true\n0 5 * * * /bin/true\n");
+        let timers = files.iter().filter(|(name, _)| name.ends_with(".timer")).map(|(name, _)| name).collect::<Vec<_>>();
+        assert_eq!(timers.len(), 2, "{:?}", timers);
+        assert_ne!(timers[0], timers[1]);
+        assert!(warnings.iter().any(|warning| warning.contains("has the same unit name as another")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn minutes_and_seconds_are_written_with_two_digits() {
+        let (files, _) = translate::<UserCrontabEntry>("5 4 * * * /bin/true\n");
+        let timer = unit(&files, ".timer");
+        assert!(timer.contains("4:05:00\n"), "{}", timer);
+
+        let (files, _) = translate::<UserCrontabEntry>("DELAY=5\n@hourly /bin/true\n");
+        let timer = unit(&files, ".timer");
+        assert!(timer.contains("OnCalendar=*-*-* *:05:00\n"), "{}", timer);
+    }
+
+    #[test]
+    fn oncalendar_overrides_the_schedule_of_the_jobs() {
+        let (files, warnings) = translate::<UserCrontabEntry>("ONCALENDAR=Mon,Fri *-*-* 9:00\n0 5 * * * /bin/true\n");
+        let timer = unit(&files, ".timer");
+        assert!(timer.contains("OnCalendar=Mon,Fri *-*-* 9:00\n"), "{}", timer);
+        assert!(!timer.contains("5:00:00"), "{}", timer);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("ONCALENDAR=$(reboot)\n0 5 * * * /bin/true\n");
+        assert!(unit(&files, ".timer").contains("5:00:00\n"));
+        assert!(warnings.iter().any(|w| w.contains("not a calendar expression")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn the_service_type_can_be_chosen() {
+        let (files, _) = translate::<UserCrontabEntry>("0 5 * * * /bin/true\n");
+        assert!(unit(&files, ".service").contains("\nType=oneshot\n"));
+
+        for kind in &["simple", "exec", "forking", "oneshot", "dbus", "notify", "idle"] {
+            let (files, warnings) = translate::<UserCrontabEntry>(&format!("SERVICE_TYPE={}\n0 5 * * * /bin/true\n", kind));
+            let service = unit(&files, ".service");
+            assert!(service.contains(&format!("\nType={}\n", kind)), "{}", service);
+            assert!(warnings.is_empty(), "{:?}", warnings);
+        }
+
+        let (files, warnings) = translate::<UserCrontabEntry>("SERVICE_TYPE=daemon\n0 5 * * * /bin/true\n");
+        assert!(unit(&files, ".service").contains("\nType=oneshot\n"));
+        assert!(warnings.iter().any(|w| w.contains("invalid SERVICE_TYPE")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn runtime_max_caps_the_runtime_of_the_service() {
+        let (files, warnings) = translate::<UserCrontabEntry>("RUNTIME_MAX=1h 30min\n0 5 * * * /bin/true\n");
+        assert!(unit(&files, ".service").contains("\nRuntimeMaxSec=1h 30min\n"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("RUNTIME_MAX=an hour\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("RuntimeMaxSec="));
+        assert!(warnings.iter().any(|w| w.contains("invalid RUNTIME_MAX")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn minutely_jobs_can_run_every_few_minutes() {
+        let (files, _) = translate::<UserCrontabEntry>("MINUTELY_STEP=5\n@minutely /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=*:0/5\n"));
+
+        let (files, _) = translate::<UserCrontabEntry>("@minutely /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=minutely\n"));
+
+        let (files, warnings) = translate::<UserCrontabEntry>("MINUTELY_STEP=60\n@minutely /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=minutely\n"));
+        assert!(warnings.iter().any(|w| w.contains("invalid MINUTELY_STEP")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn each_job_has_the_delay_set_above_it() {
+        let (files, _) = translate::<UserCrontabEntry>("DELAY=5\n@daily /bin/true\nDELAY=10\n@daily /bin/false\n");
+        let job = |command: &str| {
+            let (name, service) = files.iter().find(|(name, unit)| name.ends_with(".service") && unit.contains(command)).unwrap();
+            (service.clone(), unit(&files, &name.replace(".service", ".timer")))
+        };
+        let ((first_service, first_timer), (second_service, second_timer)) = (job("/bin/true"), job("/bin/false"));
+        assert!(first_timer.contains("0:05:00\n"), "{}", first_timer);
+        assert!(first_service.contains("/boot-delay 5\n"), "{}", first_service);
+        assert!(second_timer.contains("0:10:00\n"), "{}", second_timer);
+        assert!(second_service.contains("/boot-delay 10\n"), "{}", second_service);
+    }
+
+    #[test]
+    fn missed_day_count_periods_are_caught_up_once() {
+        // systemd starts a persistent timer's service once on resume, however many
+        // elapses it missed, as long as there is only the one calendar to miss
+        for &days in [3, 30, 90, 400].iter() {
+            let (files, _) = translate::<AnacrontabEntry>(&format!("{} 0 job /bin/true\n", days));
+            let timer = unit(&files, ".timer");
+            assert_eq!(files.iter().filter(|(name, _)| name.ends_with(".timer")).count(), 1);
+            assert_eq!(timer.lines().filter(|l| l.starts_with("OnCalendar=")).count(), 1, "{}", timer);
+            if cfg!(feature = "persistent") {
+                assert!(timer.contains("\nPersistent=true\n"), "{}", timer);
+            }
+            assert!(!timer.contains("OnBootSec=") && !timer.contains("OnUnitInactiveSec="), "{}", timer);
+        }
+    }
+
+    #[test]
+    fn the_shell_flags_can_be_chosen() {
+        let (files, warnings) = translate::<UserCrontabEntry>("SHELL=/bin/bash\nSHELL_ARGS=-lc\n0 5 * * * echo $HOME isn't here\n");
+        let service = unit(&files, ".service");
+        assert!(service.contains("\nExecStart=/bin/bash -lc 'echo $$HOME isn\\'t here'\n"), "{}", service);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("SHELL=/bin/bash\nSHELL_ARGS=-l; rm\n0 5 * * * /bin/true\n");
+        let service = unit(&files, ".service");
+        assert!(service.lines().filter(|l| l.starts_with("ExecStart=")).all(|l| !l.contains("-l;")), "{}", service);
+        assert!(warnings.iter().any(|w| w.contains("invalid SHELL_ARGS")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn the_strict_hardening_profile_sandboxes_the_service() {
+        let (files, warnings) = translate::<UserCrontabEntry>("HARDENING=strict\n0 5 * * * /bin/true\n");
+        let service = unit(&files, ".service");
+        for setting in STRICT_HARDENING.iter() {
+            assert!(service.contains(&format!("\n{}\n", setting)), "{}", service);
+        }
+        assert!(service.contains("\nRestrictAddressFamilies=AF_UNIX AF_INET AF_INET6\n"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, _) = translate::<UserCrontabEntry>("0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("ProtectKernelTunables="));
+
+        let (files, warnings) = translate::<UserCrontabEntry>("HARDENING=paranoid\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("ProtectKernelTunables="));
+        assert!(warnings.iter().any(|w| w.contains("unknown HARDENING profile")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn units_say_which_generator_wrote_them() {
+        let (files, _) = translate::<UserCrontabEntry>("0 5 * * * /bin/true\n");
+        let header = format!("# Generated by systemd-crontab-generator {}\n", VERSION);
+        for suffix in &[".service", ".timer"] {
+            let unit = unit(&files, suffix);
+            assert!(unit.starts_with(&header), "{}", unit);
+        }
+    }
+
+    #[test]
+    fn random_delays_can_span_the_whole_period() {
+        let delay = if cfg!(feature = "randomized-delay") { "RandomizedDelaySec" } else { "AccuracySec" };
+        for &(crontab, length) in [("RANDOM_DELAY=random\n@daily /bin/true\n", "1d"),
+                                   ("RANDOM_DELAY=period\n@weekly /bin/true\n", "1w"),
+                                   ("RANDOM_DELAY=random\n0,20,40 * * * * /bin/true\n", "20min"),
+                                   ("RANDOM_DELAY=random\n0 9,17 * * * /bin/true\n", "8h")]
+                                      .iter() {
+            let (files, _) = translate::<UserCrontabEntry>(crontab);
+            let timer = unit(&files, ".timer");
+            assert!(timer.contains(&format!("\n{}={}\n", delay, length)), "{}", timer);
+        }
+
+        let (files, warnings) = translate::<UserCrontabEntry>("RANDOM_DELAY=random\n@reboot /bin/true\n");
+        assert!(!unit(&files, ".timer").contains(&format!("{}=", delay)));
+        assert!(warnings.iter().any(|w| w.contains("no period to spread over")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn stopping_cron_target_stops_the_jobs() {
+        let (files, _) = translate::<UserCrontabEntry>("0 5 * * * /bin/true\n");
+        for suffix in &[".service", ".timer"] {
+            let unit = unit(&files, suffix);
+            assert!(unit.contains("\nPartOf=cron.target\n"), "{}", unit);
+        }
+    }
+
+    #[test]
+    fn intervals_count_from_the_end_of_the_last_run() {
+        let (files, warnings) = translate::<UserCrontabEntry>("INTERVAL=30min\n@hourly /bin/true\n");
+        let timer = unit(&files, ".timer");
+        assert!(timer.contains("\nOnUnitInactiveSec=30min\n"), "{}", timer);
+        assert!(timer.contains("\nOnBootSec="), "{}", timer);
+        assert!(!timer.contains("OnCalendar="), "{}", timer);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("INTERVAL=half an hour\n@hourly /bin/true\n");
+        let timer = unit(&files, ".timer");
+        assert!(timer.contains("OnCalendar=hourly\n") && !timer.contains("OnUnitInactiveSec="), "{}", timer);
+        assert!(warnings.iter().any(|w| w.contains("invalid INTERVAL")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn variable_values_are_escaped_for_environment() {
+        let (files, _) = translate::<UserCrontabEntry>("A=50%\nB=$HOME/x ${PATH}\nC=a \\b \"c\"\n0 5 * * * /bin/true\n");
+        let service = unit(&files, ".service");
+        // specifiers are expanded in Environment=, but variables aren't
+        assert!(service.contains("\nEnvironment=\"A=50%%\"\n"), "{}", service);
+        assert!(service.contains("\nEnvironment=\"B=$HOME/x ${PATH}\"\n"), "{}", service);
+        assert!(service.contains("\nEnvironment=\"C=a \\\\b \\\"c\\\"\"\n"), "{}", service);
+    }
+
+    #[test]
+    fn jobs_can_be_kept_to_the_first_boot() {
+        let (files, _) = translate::<UserCrontabEntry>("FIRST_BOOT_ONLY=yes\n@reboot /usr/sbin/provision\n");
+        let service = unit(&files, ".service");
+        assert!(service.contains("\nConditionFirstBoot=true\n"), "{}", service);
+
+        let (files, _) = translate::<UserCrontabEntry>("@reboot /usr/sbin/provision\n");
+        assert!(!unit(&files, ".service").contains("ConditionFirstBoot="));
+    }
+
+    #[test]
+    fn cpu_quota_caps_the_cpu_time_of_the_service() {
+        let (files, warnings) = translate::<UserCrontabEntry>("CPU_QUOTA=50%\n0 5 * * * /bin/true\n");
+        assert!(unit(&files, ".service").contains("\nCPUQuota=50%\n"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        for quota in &["50", "half%", "0%"] {
+            let (files, warnings) = translate::<UserCrontabEntry>(&format!("CPU_QUOTA={}\n0 5 * * * /bin/true\n", quota));
+            assert!(!unit(&files, ".service").contains("CPUQuota="));
+            assert!(warnings.iter().any(|w| w.contains("invalid CPU_QUOTA")), "{:?}", warnings);
+        }
+    }
+
+    #[test]
+    fn random_delays_can_be_fixed() {
+        let (files, _) = translate::<UserCrontabEntry>("RANDOM_DELAY=30\nFIXED_RANDOM_DELAY=yes\n@daily /bin/true\n");
+        let timer = unit(&files, ".timer");
+        if cfg!(feature = "randomized-delay") {
+            assert!(timer.contains("\nRandomizedDelaySec=30m\nFixedRandomDelay=true\n"), "{}", timer);
+        } else {
+            assert!(!timer.contains("FixedRandomDelay="), "{}", timer);
+        }
+
+        let (files, _) = translate::<UserCrontabEntry>("RANDOM_DELAY=30\n@daily /bin/true\n");
+        assert!(!unit(&files, ".timer").contains("FixedRandomDelay="));
+    }
+
+    #[test]
+    fn memory_use_can_be_limited() {
+        let (files, warnings) = translate::<UserCrontabEntry>("MEMORY_HIGH=512M\nMEMORY_MAX=1.5G\n0 5 * * * /bin/true\n");
+        let service = unit(&files, ".service");
+        assert!(service.contains("\nMemoryHigh=512M\nMemoryMax=1.5G\n"), "{}", service);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("MEMORY_MAX=2 gigs\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("MemoryMax="));
+        assert!(warnings.iter().any(|w| w.contains("invalid MEMORY_MAX")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn named_jobs_can_be_started_by_their_name() {
+        let dir = env::temp_dir().join(format!("cron-generate-test-{}-alias", ::std::process::id()));
+        let (path, dstdir) = (dir.join("crontab"), dir.join("out"));
+        create_dir_all(&dstdir).unwrap();
+        File::create(&path)
+            .and_then(|mut file| file.write_all(b"NAME=mybackup\n0 5 * * * /bin/true\nNAME=my backup\n@daily /bin/true\n"))
+            .unwrap();
+        let summary = process_crontab_file::<UserCrontabEntry, _>(&path, &GeneratorConfig::new(&dstdir));
+        let target = read_link(dstdir.join("mybackup.service"));
+        let aliases = dstdir.read_dir().unwrap().filter(|f| !f.as_ref().unwrap().file_name().to_str().unwrap().starts_with("cron")).count();
+        remove_dir_all(&dir).unwrap();
+        assert_eq!(summary.translated, 2);
+        let target = target.unwrap();
+        assert!(target.file_name().unwrap().to_str().unwrap().starts_with("cron-"), "{}", target.display());
+        assert_eq!(aliases, 1);
+    }
+
+    #[test]
+    fn calendars_of_named_periods_get_their_name() {
+        for &(crontab, period) in [("0 0 1 1 * /bin/true\n", "yearly"),
+                                   ("0 0 1 * * /bin/true\n", "monthly"),
+                                   ("0 0 * * 1 /bin/true\n", "weekly"),
+                                   ("0 0 * * * /bin/true\n", "daily")]
+                                      .iter() {
+            let (files, _) = translate::<UserCrontabEntry>(crontab);
+            let timer = unit(&files, ".timer");
+            assert!(timer.contains(&format!("\nOnCalendar={}\n", period)), "{}", timer);
+        }
+
+        let (files, _) = translate::<UserCrontabEntry>("0 0 2 1 * /bin/true\n");
+        let timer = unit(&files, ".timer");
+        assert!(timer.contains("-2 00:00:00\n"), "{}", timer);
+    }
+
+    #[test]
+    fn timers_quote_the_line_they_come_from() {
+        let (files, _) = translate::<UserCrontabEntry>("FOO=bar\n0  5 * * *   /bin/echo  'a  b'\n");
+        let timer = unit(&files, ".timer");
+        assert!(timer.contains(":2\n# 0  5 * * *   /bin/echo  'a  b'\n"), "{}", timer);
+    }
+
+    #[test]
+    fn start_hours_past_the_end_of_the_day_are_ignored() {
+        let (files, warnings) = translate::<AnacrontabEntry>("START_HOURS_RANGE=3-22\n1 5 daily /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=*-*-* 03:05:00\n"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<AnacrontabEntry>("START_HOURS_RANGE=23-0\n1 5 daily /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=*-*-* 23:05:00\n"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<AnacrontabEntry>("START_HOURS_RANGE=25-30\n1 5 daily /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=*-*-* 00:05:00\n"));
+        assert!(warnings.iter().any(|w| w.contains("invalid START_HOURS_RANGE")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn jobs_can_run_on_the_last_weekday_of_the_month() {
+        let (files, warnings) = translate::<UserCrontabEntry>("LAST_WEEKDAY=yes\n0 9 * * 5 /usr/bin/payroll\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=Fri *-*~07/1 09:00:00\n"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("LAST_WEEKDAY=yes\n0 9 1 * * /usr/bin/payroll\n");
+        assert!(!unit(&files, ".timer").contains("~07"));
+        assert!(warnings.iter().any(|w| w.contains("ignoring LAST_WEEKDAY")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn exit_statuses_can_count_as_success() {
+        let (files, warnings) = translate::<UserCrontabEntry>("SUCCESS_EXIT=1\n0 5 * * * /bin/true\n");
+        assert!(unit(&files, ".service").contains("\nSuccessExitStatus=1\n"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("SUCCESS_EXIT=1, 2 SIGHUP -3\n0 5 * * * /bin/true\n");
+        assert!(unit(&files, ".service").contains("\nSuccessExitStatus=1 2 SIGHUP\n"));
+        assert_eq!(warnings, ["ignoring invalid exit status in SUCCESS_EXIT: \"-3\""]);
+    }
+
+    #[test]
+    fn disabled_jobs_have_units_but_nothing_starts_them() {
+        let dir = env::temp_dir().join(format!("cron-generate-test-{}-disabled", ::std::process::id()));
+        let (path, dstdir) = (dir.join("crontab"), dir.join("out"));
+        create_dir_all(&dstdir).unwrap();
+        File::create(&path).and_then(|mut file| file.write_all(b"0 5 * * * /bin/true\nDISABLED=yes\n0 6 * * * /bin/false\n")).unwrap();
+        let summary = process_crontab_file::<UserCrontabEntry, _>(&path, &GeneratorConfig::new(&dstdir));
+        let wants = dstdir.join("cron.target.wants").read_dir().unwrap().count();
+        remove_dir_all(&dir).unwrap();
+        assert_eq!(summary.translated, 2);
+        assert_eq!(wants, 1);
+        let disabled = &summary.jobs[1].files;
+        assert!(disabled.iter().any(|file| file.to_string_lossy().ends_with(".timer")), "{:?}", disabled);
+        assert!(disabled.iter().all(|file| !file.starts_with("cron.target.wants")), "{:?}", disabled);
+    }
+
+    #[test]
+    fn calendars_can_be_pinned_to_a_year() {
+        for &(crontab, calendar) in [("YEAR=2025\n30 2 * * 1 /bin/true\n", "Mon 2025-*-* 02:30:00"),
+                                     ("YEAR=2025,2027\n@daily /bin/true\n", "2025,2027-*-* 00:00:00"),
+                                     ("YEAR=2025\nMINUTELY_STEP=5\n@minutely /bin/true\n", "2025-*-* *:0/5")]
+                                        .iter() {
+            let (files, warnings) = translate::<UserCrontabEntry>(crontab);
+            let timer = unit(&files, ".timer");
+            assert!(timer.contains(&format!("\nOnCalendar={}\n", calendar)), "{}", timer);
+            assert!(warnings.is_empty(), "{:?}", warnings);
+        }
+
+        let (files, warnings) = translate::<UserCrontabEntry>("YEAR=next\n@daily /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=daily\n"));
+        assert!(warnings.iter().any(|w| w.contains("invalid YEAR")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn jobs_can_name_jobs_of_other_crontabs() {
+        let config = GeneratorConfig { owner: Some(0), ..GeneratorConfig::new("-").dry_run(true) };
+        let mut summary = process_crontab_str::<UserCrontabEntry, _>("AFTER=fetch.service\n0 5 * * * /bin/report\n", "a", &config);
+        summary.merge(process_crontab_str::<UserCrontabEntry, _>("NAME=fetch\n0 4 * * * /bin/fetch\n", "b", &config));
+        resolve_dependencies(&mut summary, &config);
+        assert!(summary.warnings.is_empty(), "{:?}", summary.warnings);
+        let fetch = summary.jobs[1].units.iter().find(|(name, _)| name.ends_with(".service")).map(|(name, _)| name.clone()).unwrap();
+        let report = unit(&summary.jobs[0].units, "dependencies.conf");
+        assert!(report.ends_with(&format!("[Unit]\nAfter={}\n", fetch)), "{}", report);
+    }
+
+    #[test]
+    fn the_output_of_jobs_can_be_discarded() {
+        let (files, warnings) = translate::<UserCrontabEntry>("OUTPUT=null\n0 5 * * * /bin/true\n");
+        let service = unit(&files, ".service");
+        assert!(service.contains("\nStandardOutput=null\n"), "{}", service);
+        assert!(!service.contains("StandardError="), "{}", service);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("OUTPUT=/dev/null\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("StandardOutput="));
+        assert!(warnings.iter().any(|w| w.contains("invalid OUTPUT")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn hourly_jobs_keep_to_the_start_hours_range() {
+        for &(range, calendar) in [("8-18", "*-*-* 08..18:05:00"), ("22-6", "*-*-* 22..23,00..06:05:00")].iter() {
+            let (files, _) = translate::<UserCrontabEntry>(&format!("START_HOURS_RANGE={}\nDELAY=5\n@hourly /bin/true\n", range));
+            let timer = unit(&files, ".timer");
+            assert!(timer.contains(&format!("\nOnCalendar={}\n", calendar)), "{}", timer);
+        }
+    }
+
+    #[test]
+    fn jobs_can_run_in_a_slice() {
+        let (files, warnings) = translate::<UserCrontabEntry>("SLICE=cron.slice\n0 5 * * * /bin/true\n");
+        assert!(unit(&files, ".service").contains("\nSlice=cron.slice\n"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, _) = translate::<UserCrontabEntry>("0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("Slice="));
+
+        let (files, warnings) = translate::<UserCrontabEntry>("SLICE=cron\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("Slice="));
+        assert!(warnings.iter().any(|w| w.contains("invalid SLICE")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn jobs_that_cant_start_in_time_are_given_up() {
+        let (files, warnings) = translate::<UserCrontabEntry>("JOB_TIMEOUT=10min\n0 5 * * * /bin/true\n");
+        let service = unit(&files, ".service");
+        assert!(service.contains("\nJobTimeoutSec=10min\n"), "{}", service);
+        assert!(service.find("JobTimeoutSec=") < service.find("[Service]"), "{}", service);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("JOB_TIMEOUT=soon\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("JobTimeoutSec="));
+        assert!(warnings.iter().any(|w| w.contains("invalid JOB_TIMEOUT")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn units_can_be_grouped_by_their_crontab() {
+        let dir = env::temp_dir().join(format!("cron-generate-test-{}-by-source", ::std::process::id()));
+        let (path, dstdir) = (dir.join("crontab"), dir.join("out"));
+        create_dir_all(&dstdir).unwrap();
+        File::create(&path).and_then(|mut file| file.write_all(b"0 5 * * * /bin/true\n")).unwrap();
+        let summary = process_crontab_file::<UserCrontabEntry, _>(&path, &GeneratorConfig::new(&dstdir).by_source(true));
+        let subdir = dir.strip_prefix("/").unwrap().join("crontab").to_str().unwrap().replace('/', "-");
+        let files = summary.jobs[0].files.clone();
+        let timer = files.iter().find(|file| file.starts_with(&subdir) && file.to_string_lossy().ends_with(".timer")).unwrap();
+        let timer = timer.file_name().unwrap().to_owned();
+        let links = (read_link(dstdir.join(&timer)), dstdir.join("cron.target.wants").join(&timer).exists());
+        remove_dir_all(&dir).unwrap();
+        assert!(files.iter().filter(|file| file.starts_with(&subdir)).count() >= 2, "{:?}", files);
+        assert_eq!(links.0.unwrap(), dstdir.join(&subdir).join(&timer));
+        assert!(links.1);
+    }
+
+    #[test]
+    fn tz_sets_the_time_zone_of_the_jobs_below() {
+        if !Path::new("/usr/share/zoneinfo/Europe/Berlin").exists() {
+            // the zone is checked against the system's
+            return;
+        }
+
+        let (files, warnings) = translate::<UserCrontabEntry>("TZ=Europe/Berlin\n0 5 * * * /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=*-*-* 05:00:00 Europe/Berlin\n"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, _) = translate::<UserCrontabEntry>("TZ=Europe/Berlin\nCRON_TZ=UTC\n@daily /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=*-*-* 00:00:00 UTC\n"));
+
+        let (files, warnings) = translate::<UserCrontabEntry>("TZ=Mars/Olympus_Mons\n0 5 * * * /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=*-*-* 05:00:00\n"));
+        assert!(warnings.iter().any(|w| w.contains("not a known time zone")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn jobs_can_run_once_a_while_after_their_timer_starts() {
+        let (files, warnings) = translate::<UserCrontabEntry>("RUN_AFTER=10min\n@daily /bin/true\n");
+        let timer = unit(&files, ".timer");
+        assert!(timer.contains("\nOnActiveSec=10min\n"), "{}", timer);
+        assert!(!timer.contains("OnCalendar=") && !timer.contains("OnBootSec="), "{}", timer);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("RUN_AFTER=later\n@daily /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=daily\n"));
+        assert!(warnings.iter().any(|w| w.contains("invalid RUN_AFTER")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn variables_can_come_from_a_file() {
+        for env_file in &["/etc/cron.env", "-/etc/optional.env"] {
+            let (files, warnings) = translate::<UserCrontabEntry>(&format!("ENV_FILE={}\n0 5 * * * /bin/true\n", env_file));
+            assert!(unit(&files, ".service").contains(&format!("\nEnvironmentFile={}\n", env_file)));
+            assert!(warnings.is_empty(), "{:?}", warnings);
+        }
+
+        let (files, warnings) = translate::<UserCrontabEntry>("ENV_FILE=cron.env\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("EnvironmentFile="));
+        assert!(warnings.iter().any(|w| w.contains("ignoring ENV_FILE")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn lines_without_a_command_are_skipped() {
+        let (files, warnings) = translate::<UserCrontabEntry>("0 5 * * *  %just input\n0 6 * * * /bin/true\n");
+        assert_eq!(files.iter().filter(|(name, _)| name.ends_with(".service")).count(), 1);
+        assert_eq!(warnings, ["skipping the line, there is no command after the schedule"]);
+    }
+
+    #[test]
+    fn watchdog_pings_are_awaited_from_running_services() {
+        let (files, warnings) = translate::<UserCrontabEntry>("SERVICE_TYPE=notify\nWATCHDOG=30s\n0 5 * * * /bin/serve\n");
+        assert!(unit(&files, ".service").contains("\nWatchdogSec=30s\n"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("WATCHDOG=30s\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("WatchdogSec="));
+        assert!(warnings.iter().any(|w| w.contains("oneshot jobs aren't watched")), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("SERVICE_TYPE=simple\nWATCHDOG=soon\n0 5 * * * /bin/serve\n");
+        assert!(!unit(&files, ".service").contains("WatchdogSec="));
+        assert!(warnings.iter().any(|w| w.contains("invalid WATCHDOG")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn weekends_are_written_as_sat_sun() {
+        for dow in &["0,6", "6,0", "sat,sun"] {
+            let (files, _) = translate::<UserCrontabEntry>(&format!("0 5 * * {} /bin/true\n", dow));
+            let timer = unit(&files, ".timer");
+            assert!(timer.contains("\nOnCalendar=Sat,Sun *-*-* 05:00:00\n"), "{}: {}", dow, timer);
+        }
+        let (files, _) = translate::<UserCrontabEntry>("0 5 * * 0-6 /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=*-*-* 05:00:00\n"));
+    }
+
+    #[test]
+    fn jobs_can_be_kept_running_on_isolate() {
+        let (files, _) = translate::<UserCrontabEntry>("0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("IgnoreOnIsolate="));
+
+        let (files, warnings) = translate::<UserCrontabEntry>("KEEP_ON_ISOLATE=yes\n0 5 * * * /bin/true\n");
+        assert!(unit(&files, ".service").contains("\nIgnoreOnIsolate=true\n"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn weekly_jobs_can_run_on_another_day() {
+        let (files, _) = translate::<UserCrontabEntry>("@weekly /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=weekly\n"));
+
+        for day in &["Sun", "0", "7", "sunday"] {
+            let (files, warnings) = translate::<UserCrontabEntry>(&format!("WEEKLY_DAY={}\n@weekly /bin/true\n", day));
+            let timer = unit(&files, ".timer");
+            assert!(timer.contains("\nOnCalendar=Sun *-*-* 00:00:00\n"), "{}: {}", day, timer);
+            assert!(warnings.is_empty(), "{:?}", warnings);
+        }
+
+        let (files, warnings) = translate::<UserCrontabEntry>("WEEKLY_DAY=someday\n@weekly /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=weekly\n"));
+        assert!(warnings.iter().any(|w| w.contains("invalid WEEKLY_DAY")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn a_touch_file_marks_the_success_of_jobs() {
+        let (files, warnings) = translate::<UserCrontabEntry>("TOUCH_ON_SUCCESS=/run/backup.ok\n0 5 * * * /bin/backup\n");
+        let service = unit(&files, ".service");
+        let start = service.find("\nExecStart=").expect(&service);
+        let post = service.find("\nExecStartPost=/bin/touch '/run/backup.ok'\n").expect(&service);
+        assert!(start < post, "{}", service);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("TOUCH_ON_SUCCESS=backup.ok\n0 5 * * * /bin/backup\n");
+        assert!(!unit(&files, ".service").contains("ExecStartPost="));
+        assert!(warnings.iter().any(|w| w.contains("TOUCH_ON_SUCCESS, expected an absolute path")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn random_delays_can_be_coalesced() {
+        let (coalesced, _) = translate::<UserCrontabEntry>("RANDOM_DELAY=30\nCOALESCE=yes\n@daily /bin/true\n");
+        let (independent, _) = translate::<UserCrontabEntry>("RANDOM_DELAY=30\n@daily /bin/true\n");
+        let (coalesced, independent) = (unit(&coalesced, ".timer"), unit(&independent, ".timer"));
+        if cfg!(feature = "randomized-delay") {
+            assert!(coalesced.contains("\nRandomizedDelaySec=30m\nAccuracySec=30m\n"), "{}", coalesced);
+            assert!(!independent.contains("AccuracySec="), "{}", independent);
+        } else {
+            // the window is the accuracy already
+            assert_eq!(coalesced.matches("\nAccuracySec=30m\n").count(), 1, "{}", coalesced);
+            assert!(independent.contains("\nAccuracySec=30m\n"), "{}", independent);
+        }
+    }
+
+    #[test]
+    fn timers_can_trigger_services_of_their_own() {
+        let (files, warnings) = translate::<UserCrontabEntry>("TRIGGERS=backup.service\n0 5 * * * /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\n[Timer]\nUnit=backup.service\n"));
+        assert!(!files.iter().any(|(name, _)| name.ends_with(".service")), "{:?}", files);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("TRIGGERS=backup\n0 5 * * * /bin/true\n");
+        assert!(files.iter().any(|(name, _)| name.ends_with(".service")), "{:?}", files);
+        assert!(warnings.iter().any(|w| w.contains("ignoring TRIGGERS, not a valid unit name")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn settings_that_cant_apply_to_a_period_are_warned_about() {
+        let (_, warnings) = translate::<UserCrontabEntry>("PERSISTENT=yes\n@reboot /bin/true\n");
+        assert!(warnings.iter().any(|w| w.contains("ignoring PERSISTENT, @reboot jobs only run at boot")), "{:?}", warnings);
+
+        let (_, warnings) = translate::<UserCrontabEntry>("START_HOURS_RANGE=3-22\n@reboot /bin/true\n");
+        assert!(warnings.iter().any(|w| w.contains("ignoring START_HOURS_RANGE, @reboot jobs run at boot")), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("PERSISTENT=yes\n@minutely /bin/true\n");
+        assert!(!unit(&files, ".timer").contains("Persistent=true"));
+        assert!(warnings.iter().any(|w| w.contains("ignoring PERSISTENT, a missed run of a @minutely job")), "{:?}", warnings);
+
+        let (_, warnings) = translate::<UserCrontabEntry>("PERSISTENT=yes\n@daily /bin/true\n");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn early_jobs_run_before_the_boot_dependencies() {
+        let (files, warnings) = translate::<UserCrontabEntry>("EARLY=yes\n@reboot /bin/true\n");
+        let service = unit(&files, ".service");
+        assert!(service.contains("\nDefaultDependencies=no\n"), "{}", service);
+        assert!(service.contains("\nBefore=sysinit.target shutdown.target\n"), "{}", service);
+        assert!(!files.iter().any(|(name, _)| name.ends_with(".timer")), "{:?}", files);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("EARLY=yes\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("DefaultDependencies=no"));
+        assert!(warnings.iter().any(|w| w.contains("ignoring EARLY, only @reboot jobs")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn installed_units_are_not_shadowed() {
+        let crontab = "0 5 * * * /bin/true\n";
+        let (files, _) = translate::<UserCrontabEntry>(crontab);
+        let timer_name = files.iter().map(|(name, _)| name.clone()).find(|name| name.ends_with(".timer")).unwrap();
+
+        let root = env::temp_dir().join(format!("cron-generate-test-{}-installed", ::std::process::id()));
+        create_dir_all(root.join("usr/lib/systemd/system")).unwrap();
+        create_dir_all(root.join("etc")).unwrap();
+        File::create(root.join("etc/passwd")).and_then(|mut file| file.write_all(b"root:x:0:0:root:/root:/bin/sh\n")).unwrap();
+        File::create(root.join("usr/lib/systemd/system").join(&timer_name)).unwrap();
+        let (files, warnings) = translate_with::<UserCrontabEntry>(crontab, GeneratorConfig::new("-").root(&root));
+        remove_dir_all(&root).unwrap();
+        assert!(files.iter().all(|(name, _)| *name != timer_name), "{:?}", files);
+        assert!(warnings.iter().any(|w| w.contains("the job has the same unit name as an installed unit")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn failed_jobs_can_be_restarted() {
+        let (files, warnings) = translate::<UserCrontabEntry>("RESTART=on-failure\nRESTART_SEC=30s\n0 5 * * * /bin/true\n");
+        let service = unit(&files, ".service");
+        assert!(service.contains("\nRestart=on-failure\n"), "{}", service);
+        assert!(service.contains("\nRestartSec=30s\n"), "{}", service);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("RESTART=always\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("Restart="));
+        assert!(warnings.iter().any(|w| w.contains("oneshot jobs can only be restarted on failure")), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("SERVICE_TYPE=simple\nRESTART=always\n0 5 * * * /bin/serve\n");
+        assert!(unit(&files, ".service").contains("\nRestart=always\n"));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("RESTART=sometimes\nRESTART_SEC=later\n0 5 * * * /bin/true\n");
+        let service = unit(&files, ".service");
+        assert!(!service.contains("Restart=") && !service.contains("RestartSec="), "{}", service);
+        assert!(warnings.iter().any(|w| w.contains("invalid RESTART:")), "{:?}", warnings);
+        assert!(warnings.iter().any(|w| w.contains("invalid RESTART_SEC:")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn jobs_sharing_a_lock_file_wait_for_each_other() {
+        let (files, warnings) = translate::<UserCrontabEntry>("LOCK=/var/lock/my backup\n0 5 * * * /bin/backup\n");
+        let service = unit(&files, ".service");
+        assert!(service.contains("\nExecStart=/usr/bin/flock '/var/lock/my backup' "), "{}", service);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("LOCK=backup.lock\n0 5 * * * /bin/backup\n");
+        assert!(!unit(&files, ".service").contains("flock"));
+        assert!(warnings.iter().any(|w| w.contains("ignoring LOCK, expected an absolute path")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn an_anacrontab_delay_of_0_is_no_delay() {
+        let (files, _) = translate::<AnacrontabEntry>("@reboot 0 boot /bin/true\n");
+        let timer = unit(&files, ".timer");
+        assert!(timer.contains("\nOnBootSec=0m\n"), "{}", timer);
+
+        let (files, _) = translate::<UserCrontabEntry>("@reboot /bin/true\n");
+        let timer = unit(&files, ".timer");
+        assert!(timer.contains("\nOnBootSec=1m\n"), "{}", timer);
+    }
+
+    #[test]
+    fn fields_without_values_are_written_as_a_star() {
+        assert_eq!(linearize::<Day, _>(&[], "*", ToString::to_string), "*");
+
+        let (files, _) = translate::<UserCrontabEntry>("30 2 * * * /bin/true\n");
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=*-*-* 02:30:00\n"));
+    }
+
+    #[test]
+    fn units_for_older_systemd_spell_ranges_out() {
+        let crontab = "START_HOURS_RANGE=8-11\nDELAY=5\n@hourly /bin/true\n";
+        let (files, _) = translate::<UserCrontabEntry>(crontab);
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=*-*-* 08..11:05:00\n"));
+
+        let (files, _) = translate_with::<UserCrontabEntry>(crontab, GeneratorConfig::new("-").compat(Some(229)));
+        let timer = unit(&files, ".timer");
+        assert!(timer.contains("\nOnCalendar=*-*-* 08,09,10,11:05:00\n"), "{}", timer);
+
+        let (files, _) = translate_with::<UserCrontabEntry>(crontab, GeneratorConfig::new("-").compat(Some(236)));
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=*-*-* 08..11:05:00\n"));
+
+        // weekday ranges are older than that
+        let (files, _) = translate_with::<UserCrontabEntry>("0 5 * * 1-5 /bin/true\n", GeneratorConfig::new("-").compat(Some(229)));
+        assert!(unit(&files, ".timer").contains("\nOnCalendar=Mon..Fri *-*-* 05:00:00\n"));
+    }
+
+    #[test]
+    fn the_default_persistence_can_be_configured() {
+        let config = || GeneratorConfig { default_persistent: Some(false), ..GeneratorConfig::new("-") };
+        let (files, _) = translate_with::<UserCrontabEntry>("@daily /bin/true\n", config());
+        let daily = unit(&files, ".timer");
+        let (files, _) = translate_with::<UserCrontabEntry>("PERSISTENT=yes\n@daily /bin/true\n", config());
+        let persistent = unit(&files, ".timer");
+        if cfg!(feature = "persistent") {
+            assert!(daily.contains("\nPersistent=false\n"), "{}", daily);
+            assert!(persistent.contains("\nPersistent=true\n"), "{}", persistent);
+        } else {
+            assert!(!daily.contains("Persistent=") && !persistent.contains("Persistent="), "{}", daily);
+        }
+    }
+
+    #[test]
+    fn jobs_can_wait_for_their_mounts() {
+        let (files, warnings) = translate::<UserCrontabEntry>("REQUIRE_MOUNT=/data /srv/backup\n0 5 * * * /bin/true\n");
+        let service = unit(&files, ".service");
+        assert!(service.contains("\nRequiresMountsFor=/data\nRequiresMountsFor=/srv/backup\n"), "{}", service);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let (files, warnings) = translate::<UserCrontabEntry>("REQUIRE_MOUNT=data\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("RequiresMountsFor="));
+        assert!(warnings.iter().any(|w| w.contains("ignoring REQUIRE_MOUNT, expected an absolute path")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn calendars_systemd_has_a_name_for_are_written_by_that_name() {
+        for &(crontab, calendar) in [("0 * * * *", "hourly"), ("0 0 * * *", "daily"), ("0 0 * * 1", "weekly"), ("0 0 1 * *", "monthly"),
+                                     ("WEEKLY_DAY=Mon\n@weekly", "weekly"), ("WEEKLY_DAY=Sun\n@weekly", "Sun *-*-* 00:00:00")]
+                                        .iter() {
+            let (files, _) = translate::<UserCrontabEntry>(&format!("{} /bin/true\n", crontab));
+            let timer = unit(&files, ".timer");
+            assert!(timer.contains(&format!("\nOnCalendar={}\n", calendar)), "{}: {}", crontab, timer);
+        }
+    }
+
+    #[test]
+    fn the_umask_of_jobs_can_be_set() {
+        let (files, _) = translate::<UserCrontabEntry>("0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("UMask="));
+
+        for &(umask, directive) in [("0027", "UMask=0027"), ("77", "UMask=0077")].iter() {
+            let (files, warnings) = translate::<UserCrontabEntry>(&format!("UMASK={}\n0 5 * * * /bin/true\n", umask));
+            assert!(unit(&files, ".service").contains(&format!("\n{}\n", directive)));
+            assert!(warnings.is_empty(), "{:?}", warnings);
+        }
+
+        let (files, warnings) = translate::<UserCrontabEntry>("UMASK=0089\n0 5 * * * /bin/true\n");
+        assert!(!unit(&files, ".service").contains("UMask="));
+        assert!(warnings.iter().any(|w| w.contains("invalid UMASK")), "{:?}", warnings);
+    }
+}
@@ -0,0 +1,24 @@
+//! Translation of crontab, anacrontab and user spool files into systemd
+//! service and timer units, as done by systemd-crontab-generator.
+//!
+//! The argument parsing and logger setup of the generator itself are only
+//! built with the `cli` feature, on by default.
+
+extern crate cronparse;
+extern crate libc;
+extern crate md5;
+extern crate pgs_files;
+extern crate rustc_serialize;
+
+#[macro_use]
+extern crate log;
+
+pub mod generate;
+pub mod process;
+
+include!(concat!(env!("OUT_DIR"), "/config.rs"));
+pub static VERSION: &str = env!("CARGO_PKG_VERSION");
+pub static SYSTEM_CRONTAB_DIR: &str = "/etc/cron.d";  // SystemCrontabEntry
+pub static SYSTEM_CRONTAB_FILE: &str = "/etc/crontab";
+pub static ANACRONTAB_FILE: &str = "/etc/anacrontab";  // AnacrontabEntry
+pub static REBOOT_FILE: &str = "/run/crond.reboot";
@@ -1,29 +1,94 @@
 extern crate cronparse;
+extern crate docopt;
 extern crate libc;
-extern crate md5;
-extern crate pgs_files;
+#[macro_use]
+extern crate serde;
+extern crate systemd_crontab_generator;
 
 #[macro_use]
 extern crate log;
 extern crate kernlog;
 
-use std::thread::spawn;
 use std::env;
+use std::thread::spawn;
 use std::fs::{File, create_dir_all, metadata};
 use std::os::unix::fs::symlink;
-use std::io::Write;
+use std::io::{self, Write};
 use std::path::Path;
+use std::process::{Command, exit};
 
 use cronparse::crontab::{AnacrontabEntry, SystemCrontabEntry, UserCrontabEntry};
+use docopt::Docopt;
+
+use systemd_crontab_generator::process::{self, GeneratorConfig, Summary};
+use systemd_crontab_generator::{ANACRONTAB_FILE, BIN_DIR, PACKAGE, REBOOT_FILE, SYSTEM_CRONTAB_DIR,
+                                SYSTEM_CRONTAB_FILE, USERS_CRONTAB_DIR, VERSION};
+
+static USAGE: &str = r#"
+Usage: systemd-crontab-generator [--summary] [--by-source] [--compat <version>] [--root <prefix>] <destination-directory> [<early-directory> <late-directory>]
+       systemd-crontab-generator --check <directory>
+       systemd-crontab-generator --preview <crontab>
+       systemd-crontab-generator --install <crontab> [--root <prefix>]
+       systemd-crontab-generator --explain <crontab>
+       systemd-crontab-generator --validate <crontab>
+       systemd-crontab-generator --report [--root <prefix>]
+       systemd-crontab-generator -h | --help
+       systemd-crontab-generator --version
+
+Generate systemd units from crontab files
 
-mod generate;
-mod process;
+Options:
 
-include!(concat!(env!("OUT_DIR"), "/config.rs"));
-static SYSTEM_CRONTAB_DIR: &'static str = "/etc/cron.d";  // SystemCrontabEntry
-static SYSTEM_CRONTAB_FILE: &'static str = "/etc/crontab";
-static ANACRONTAB_FILE: &'static str = "/etc/anacrontab";  // AnacrontabEntry
-static REBOOT_FILE: &'static str = "/run/crond.reboot";
+  -h, --help             Show this help message and exit.
+  --version              Show the version and build options and exit.
+  --summary              Print a single machine-readable line with the number
+                         of files, jobs, skipped entries and errors at the end.
+  --by-source            Write the units of each crontab into a subdirectory
+                         named after it, linked from the destination directory.
+  --compat <version>     Write units for an older systemd of the given version,
+                         leaving out what it doesn't support.
+  --root <prefix>        Read the crontabs of, and write the units into, the
+                         system installed below prefix, e.g. in a chroot.
+  --check <directory>    Parse all crontabs in the directory (in /etc/cron.d
+                         format) and report how they translate, without
+                         writing any units. Exits nonzero if any entry was
+                         skipped. If the directory is "-", a single user
+                         crontab is read from standard input instead.
+  --preview <crontab>    Translate a user crontab and print the calendar
+                         expressions of each entry, along with their next
+                         elapses when systemd-analyze is available.
+  --explain <crontab>    Translate a user crontab and print how the fields
+                         of each entry map to its calendar expression, and
+                         the variables that affect it.
+  --validate <crontab>   Translate a user crontab and only report its first
+                         error, with its line and column, exiting nonzero
+                         if there is one.
+  --report               Translate the crontabs of the system and print the
+                         next run of every job, soonest first, when
+                         systemd-analyze is available.
+  --install <crontab>    After confirmation, translate a crontab (in
+                         /etc/cron.d format) into units in
+                         /etc/systemd/system, reload systemd and enable
+                         cron.target, to move the jobs to native timers.
+                         With --root, cron.target is only enabled in the
+                         system below prefix.
+"#;
+
+#[derive(Debug, Deserialize)]
+struct Args {
+    arg_destination_directory: Option<String>,
+    arg_early_directory: Option<String>,
+    flag_check: Option<String>,
+    flag_preview: Option<String>,
+    flag_install: Option<String>,
+    flag_explain: Option<String>,
+    flag_validate: Option<String>,
+    flag_report: bool,
+    flag_summary: bool,
+    flag_by_source: bool,
+    flag_compat: Option<u32>,
+    flag_root: Option<String>,
+}
 
 macro_rules! try_ {
     ($exp:expr) => {
@@ -35,44 +100,356 @@ macro_rules! try_ {
 }
 
 fn main() {
-    log::set_logger(|filter| kernlog::KernelLog::init_level(log::LogLevelFilter::Error, filter)).unwrap();
+    let args: Args = Docopt::new(USAGE)
+        .and_then(|d| d.version(Some(version())).deserialize())
+        .unwrap_or_else(|e| e.exit());
+
+    if let Some(ref dir) = args.flag_check {
+        exit(check(dir));
+    }
+
+    if let Some(ref crontab) = args.flag_preview {
+        exit(preview(crontab));
+    }
 
-    let dest_dir = match env::args().nth(1) {
-        None => {
-            println!("Usage: systemd-crontab-generator <destination-directory>");
-            return;
+    if let Some(ref crontab) = args.flag_explain {
+        exit(explain(crontab));
+    }
+
+    if let Some(ref crontab) = args.flag_validate {
+        exit(validate(crontab));
+    }
+
+    if args.flag_report {
+        exit(report(&args.flag_root.unwrap_or_else(|| "/".to_owned())));
+    }
+
+    if let Some(ref crontab) = args.flag_install {
+        exit(install(crontab, &args.flag_root.clone().unwrap_or_else(|| "/".to_owned())));
+    }
+
+    let logger = kernlog::KernelLog::with_level(log::LevelFilter::Error).unwrap();
+    log::set_boxed_logger(Box::new(logger)).unwrap();
+    log::set_max_level(log::LevelFilter::Error);
+
+    let config = GeneratorConfig::new(args.arg_destination_directory.unwrap())
+                     .root(args.flag_root.unwrap_or_else(|| "/".to_owned()))
+                     .by_source(args.flag_by_source)
+                     .compat(args.flag_compat);
+    // set for the generators with ManagerEnvironment= in systemd-system.conf(5)
+    let default_persistent = env::var("DEFAULT_PERSISTENT").ok().and_then(|v| {
+        match &*v.trim().to_lowercase() {
+            "yes" | "true" | "1" => Some(true),
+            "no" | "false" | "0" => Some(false),
+            _ => None,
         }
-        Some(path) => path,
+    });
+    let config = GeneratorConfig { default_persistent, ..config };
+    let config = GeneratorConfig {
+        dstdir: config.rooted(&config.dstdir),
+        early_dir: args.arg_early_directory.map(|dir| config.rooted(dir)),
+        ..config
     };
+    // the flag file is only there once a run at boot made it, checked before
+    // this run makes it as well
+    let reloading = metadata(config.rooted(REBOOT_FILE)).map(|m| m.is_file()).unwrap_or(false);
+    let config = config.reloading(reloading);
+
+    if let Err(err) = process::check_dstdir(&config) {
+        error!("can't write units into {}: {}", config.dstdir.display(), err);
+        exit(1);
+    }
 
-    let s = dest_dir.clone();
+    let c = config.clone();
     let user_thread = spawn(move || {
-        if !metadata(USERS_CRONTAB_DIR).map(|m| m.is_dir()).unwrap_or(false) {
-            return generate_after_var_unit(&*s);
+        match process::process_spool_dirs(&c) {
+            None => {
+                generate_after_var_unit(&c);
+                Summary::default()
+            }
+            Some(summary) => {
+                create_reboot_lock_file(&c.rooted(REBOOT_FILE));
+                summary
+            }
         }
-
-        process::process_crontab_dir::<UserCrontabEntry, _>(USERS_CRONTAB_DIR, s);
-        create_reboot_lock_file();
     });
 
-    let s = dest_dir.clone();
+    let c = config.clone();
     let system_thread = spawn(move || {
-        process::process_crontab_file::<SystemCrontabEntry, _, _>(SYSTEM_CRONTAB_FILE, &s);
-        process::process_crontab_dir::<SystemCrontabEntry, _>(SYSTEM_CRONTAB_DIR, &s);
+        let mut summary = process::process_crontab_file::<SystemCrontabEntry, _>(c.rooted(SYSTEM_CRONTAB_FILE), &c);
+        summary.merge(process::process_crontab_dir::<SystemCrontabEntry, _>(c.rooted(SYSTEM_CRONTAB_DIR), &c));
+        summary
     });
 
-    let s = dest_dir.clone();
-    let anacron_thread = spawn(move || {
-        process::process_crontab_file::<AnacrontabEntry, _, _>(ANACRONTAB_FILE, &s);
-    });
+    let c = config.clone();
+    let anacron_thread = spawn(move || process::process_crontab_file::<AnacrontabEntry, _>(c.rooted(ANACRONTAB_FILE), &c));
+
+    let mut summary = Summary::default();
+    for thread in [user_thread, system_thread, anacron_thread] {
+        if let Ok(thread_summary) = thread.join() {
+            summary.merge(thread_summary);
+        }
+    }
+    // a job can name one in any crontab, so the names are looked up once all are read
+    process::resolve_dependencies(&mut summary, &config);
+
+    match process::remove_stale_files(&summary, &config) {
+        Ok(0) => (),
+        Ok(removed) => info!("removed {} stale files", removed),
+        Err(err) => error!("error reading previous manifest: {}", err),
+    }
+    if let Err(err) = process::write_manifest(&summary, &config) {
+        error!("error writing manifest: {}", err);
+    }
+
+    if args.flag_summary {
+        println!("systemd-crontab-generator: {}", summary);
+    }
+}
+
+fn version() -> String {
+    let features = [("persistent", cfg!(feature = "persistent")), ("randomized-delay", cfg!(feature = "randomized-delay"))];
+    format!("systemd-crontab-generator {} ({}, {})",
+            VERSION,
+            PACKAGE,
+            features.iter()
+                    .map(|&(name, enabled)| format!("{}{}", if enabled { "+" } else { "-" }, name))
+                    .collect::<Vec<_>>()
+                    .join(" "))
+}
+
+fn check(dir: &str) -> i32 {
+    let config = GeneratorConfig::new(dir).dry_run(true);
+    let mut summary = if dir == "-" {
+        // reading through the device node also makes the caller the crontab's owner
+        process::process_crontab_file::<UserCrontabEntry, _>("/dev/stdin", &config)
+    } else {
+        process::process_crontab_dir::<SystemCrontabEntry, _>(dir, &config)
+    };
+    process::resolve_dependencies(&mut summary, &config);
+
+    println!("files processed: {}", summary.files);
+    println!("entries translated: {}", summary.translated);
+    println!("entries skipped: {}", summary.skipped.len());
+    for (path, reason) in summary.skipped.iter() {
+        println!("  {}: {}", path.display(), reason);
+    }
+    println!("warnings: {}", summary.warnings.len());
+    for warning in summary.warnings.iter() {
+        println!("  {}", warning);
+    }
+    println!("env vars seen: {}", summary.env_vars.iter().cloned().collect::<Vec<_>>().join(", "));
+
+    if summary.skipped.is_empty() { 0 } else { 1 }
+}
+
+fn preview(crontab: &str) -> i32 {
+    let config = GeneratorConfig::new(crontab).dry_run(true);
+    let mut summary = process::process_crontab_file::<UserCrontabEntry, _>(crontab, &config);
+    process::resolve_dependencies(&mut summary, &config);
+
+    let mut analyze = true;
+    for job in summary.jobs.iter() {
+        println!("{}:{}: {}", job.path.display(), job.lineno, job.line);
+        if job.calendars.is_empty() {
+            println!("  at boot");
+        }
+        for calendar in job.calendars.iter() {
+            println!("  OnCalendar={}", calendar);
+            if !analyze {
+                continue;
+            }
+            match next_elapses(calendar, 3) {
+                Ok(elapses) => {
+                    for elapse in elapses {
+                        println!("    {}", elapse);
+                    }
+                }
+                Err(err) => {
+                    println!("  (next elapses unavailable, can't run systemd-analyze: {})", err);
+                    analyze = false;
+                }
+            }
+        }
+    }
+    for (path, reason) in summary.skipped.iter() {
+        println!("skipped in {}: {}", path.display(), reason);
+    }
+    for warning in summary.warnings.iter() {
+        println!("warning: {}", warning);
+    }
+
+    if summary.errors == 0 { 0 } else { 1 }
+}
+
+/// The `Next elapse:` and `Iter. #` lines systemd-analyze prints for the next
+/// `iterations` elapses of a calendar expression.
+fn next_elapses(calendar: &str, iterations: usize) -> io::Result<Vec<String>> {
+    let output = Command::new("systemd-analyze")
+                     .arg("calendar")
+                     .arg(format!("--iterations={}", iterations))
+                     .arg(calendar)
+                     .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+           .lines()
+           .map(str::trim)
+           .filter(|l| l.starts_with("Next elapse:") || l.starts_with("Iter. #"))
+           .map(str::to_owned)
+           .collect())
+}
+
+fn report(root: &str) -> i32 {
+    let config = GeneratorConfig::new(root).root(root).dry_run(true);
+    let mut summary = process::process_spool_dirs(&config).unwrap_or_default();
+    summary.merge(process::process_crontab_file::<SystemCrontabEntry, _>(config.rooted(SYSTEM_CRONTAB_FILE), &config));
+    summary.merge(process::process_crontab_dir::<SystemCrontabEntry, _>(config.rooted(SYSTEM_CRONTAB_DIR), &config));
+    summary.merge(process::process_crontab_file::<AnacrontabEntry, _>(config.rooted(ANACRONTAB_FILE), &config));
+    process::resolve_dependencies(&mut summary, &config);
+
+    let mut runs = Vec::new();
+    let mut at_boot = Vec::new();
+    for job in summary.jobs.iter() {
+        if job.calendars.is_empty() {
+            at_boot.push(job);
+        }
+        for calendar in job.calendars.iter() {
+            match next_elapses(calendar, 1) {
+                Ok(elapses) => {
+                    // e.g. "Next elapse: Mon 2026-10-19 09:00:00 UTC", sorted without the weekday
+                    let next = elapses.first().map(|e| e.trim_start_matches("Next elapse:").trim().to_owned());
+                    runs.push((next.unwrap_or_else(|| "never".to_owned()), job));
+                }
+                Err(err) => {
+                    println!("next runs unavailable, can't run systemd-analyze: {}", err);
+                    return 1;
+                }
+            }
+        }
+    }
+    runs.sort_by_key(|&(ref next, job)| (next == "never", next.split_once(' ').map(|x| x.1).unwrap_or("").to_owned(), job.lineno));
+
+    println!("{:<32} {:<40} JOB", "NEXT", "SOURCE");
+    for &(ref next, job) in runs.iter() {
+        println!("{:<32} {:<40} {}", next, format!("{}:{}", job.path.display(), job.lineno), job.line);
+    }
+    for job in at_boot {
+        println!("{:<32} {:<40} {}", "at boot", format!("{}:{}", job.path.display(), job.lineno), job.line);
+    }
+    for warning in summary.warnings.iter() {
+        println!("warning: {}", warning);
+    }
+
+    if summary.errors == 0 { 0 } else { 1 }
+}
+
+fn explain(crontab: &str) -> i32 {
+    let config = GeneratorConfig::new(crontab).dry_run(true);
+    let mut summary = process::process_crontab_file::<UserCrontabEntry, _>(crontab, &config);
+    process::resolve_dependencies(&mut summary, &config);
+
+    for job in summary.jobs.iter() {
+        println!("{}:{}: {}", job.path.display(), job.lineno, job.line);
+        if job.fields.is_empty() {
+            println!("  period {}", job.line.split_whitespace().next().unwrap_or(""));
+        } else {
+            for (&(name, ref translated), written) in job.fields.iter().zip(job.line.split_whitespace()) {
+                println!("  {:<12} {:<12} -> {}", name, written, translated);
+            }
+            if job.fields[2].1 != "*" && job.fields[4].1 != "*" {
+                println!("  note: cron runs a job when either its day of month or its day of week matches,");
+                println!("        the timer only when both do");
+            }
+        }
+        for (name, value) in job.schedule_vars.iter() {
+            println!("  {}={}", name, value);
+        }
+        if job.calendars.is_empty() {
+            println!("  no calendar, the timer counts from boot or the last run");
+        }
+        for calendar in job.calendars.iter() {
+            println!("  OnCalendar={}", calendar);
+        }
+    }
+    for warning in summary.warnings.iter() {
+        println!("warning: {}", warning);
+    }
+
+    if summary.errors == 0 { 0 } else { 1 }
+}
+
+fn validate(crontab: &str) -> i32 {
+    let config = GeneratorConfig::new(crontab).dry_run(true);
+    let summary = process::process_crontab_file::<UserCrontabEntry, _>(crontab, &config);
+    if summary.errors == 0 {
+        return 0;
+    }
+
+    // lines that didn't parse are the errors that have a column
+    match summary.warnings.iter().find(|w| w.column.is_some()).or_else(|| summary.warnings.first()) {
+        Some(warning) => println!("{}", warning),
+        None => println!("{}: can't be read", crontab),
+    }
+    1
+}
+
+fn install(crontab: &str, root: &str) -> i32 {
+    let config = GeneratorConfig::new("/etc/systemd/system").root(root);
+    let unit_dir = config.rooted(&config.dstdir);
+    print!("Install the jobs of {} as units in {} and enable cron.target? [y/N] ", crontab, unit_dir.display());
+    let _ = io::stdout().flush();
+    let mut answer = String::new();
+    if io::stdin().read_line(&mut answer).is_err() || !answer.trim().eq_ignore_ascii_case("y") {
+        println!("nothing installed");
+        return 1;
+    }
+
+    // the unit directory is only for units, scripts of commands go with the package's state
+    let config = GeneratorConfig {
+        dstdir: unit_dir.clone(),
+        script_dir: Some(config.rooted(format!("/var/lib/{}", PACKAGE))),
+        ..config
+    };
+    let summary = process::process_crontab_file::<SystemCrontabEntry, _>(crontab, &config);
+    for warning in summary.warnings.iter() {
+        println!("warning: {}", warning);
+    }
+    for job in summary.jobs.iter() {
+        for file in job.files.iter() {
+            println!("installed {}", unit_dir.join(file).display());
+        }
+    }
+    if summary.translated == 0 {
+        println!("no jobs to install");
+        return 1;
+    }
+
+    // a system below a root isn't running, its units are only enabled
+    let root_arg = format!("--root={}", root);
+    let commands = if config.root == Path::new("/") {
+        vec![vec!["daemon-reload"], vec!["enable", "--now", "cron.target"]]
+    } else {
+        vec![vec![&*root_arg, "enable", "cron.target"]]
+    };
+    for args in commands.iter() {
+        match Command::new("systemctl").args(args).status() {
+            Ok(status) if status.success() => (),
+            Ok(status) => {
+                println!("systemctl {} failed: {}", args.join(" "), status);
+                return 1;
+            }
+            Err(err) => {
+                println!("can't run systemctl {}: {}", args.join(" "), err);
+                return 1;
+            }
+        }
+    }
 
-    let _ = user_thread.join();
-    let _ = system_thread.join();
-    let _ = anacron_thread.join();
+    if summary.errors == 0 { 0 } else { 1 }
 }
 
-fn generate_after_var_unit(dest_dir: &str) {
-    let cron_after_var_unit_path = Path::new(dest_dir).join("cron-after-var.service");
+fn generate_after_var_unit(config: &GeneratorConfig) {
+    let dest_dir = &config.dstdir;
+    let cron_after_var_unit_path = dest_dir.join("cron-after-var.service");
     let mut cron_after_var_unit_file = try_!(File::create(&cron_after_var_unit_path));
     try_!(writeln!(cron_after_var_unit_file,
                    r###"[Unit]
@@ -87,13 +464,13 @@ ExecStart=/bin/sh -c "{bindir}/systemctl daemon-reload ; {bindir}/systemctl try-
                    statedir = USERS_CRONTAB_DIR,
                    bindir = BIN_DIR));
 
-    let multiuser_wants_path = Path::new(dest_dir).join("multi-user.target.wants");
+    let multiuser_wants_path = dest_dir.join("multi-user.target.wants");
     try_!(create_dir_all(&multiuser_wants_path));
-    try_!(symlink(cron_after_var_unit_path, multiuser_wants_path.join("cron-after-var.service")));
+    try_!(symlink(config.unrooted(cron_after_var_unit_path), multiuser_wants_path.join("cron-after-var.service")));
 }
 
-fn create_reboot_lock_file() {
-    if let Err(err) = File::create(REBOOT_FILE) {
-        warn!("error creating lock file {}: {}", REBOOT_FILE, err);
+fn create_reboot_lock_file(path: &Path) {
+    if let Err(err) = File::create(path) {
+        warn!("error creating lock file {}: {}", path.display(), err);
     }
 }
@@ -1,19 +1,292 @@
+use std::any::TypeId;
+use std::borrow::Cow;
 use std::convert::AsRef;
-use std::fs::{metadata, read_dir};
+use std::error::Error;
+use std::fmt;
+use std::fs::{File, create_dir_all, metadata, read_dir, read_link, remove_dir, remove_file, rename};
+use std::io::{self, Read, Write};
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 
-use cronparse::{CrontabFile, CrontabFileError, CrontabFileErrorKind};
-use cronparse::crontab::{CrontabEntry, EnvVarEntry};
+use cronparse::CrontabFileError;
+use cronparse::crontab::{AnacrontabEntry, CrontabEntry, EnvVarEntry, SystemCrontabEntry, UserCrontabEntry};
+use libc::getuid;
+use pgs_files::passwd::{PasswdEntry, get_entry_by_name_from_path, get_entry_by_uid_from_path};
 
-use generate::generate_systemd_units;
+use generate::{Dependency, EntrySource, Outcome, calendar_fields, generate_systemd_units, write_dependencies};
+use super::USERS_CRONTAB_DIR;
 
-pub fn process_crontab_dir<T: FromStr, D: AsRef<Path>>(srcdir: &str, dstdir: D)
+pub static MANIFEST_FILE: &str = "cron-generated.manifest";
+
+static SCHEDULE_VARS: [&str; 15] = ["DELAY", "START_HOURS_RANGE", "PERSISTENT", "RANDOM_DELAY", "FIXED_RANDOM_DELAY",
+                                    "MINUTELY_STEP", "ONCALENDAR", "INTERVAL", "LAST_WEEKDAY", "WEEK_START", "YEAR",
+                                    "CRON_TZ", "TZ", "RUN_AFTER", "WEEKLY_DAY"];
+
+/// Settings shared by everything processed in one generator run.
+#[derive(Debug, Clone)]
+pub struct GeneratorConfig {
+    /// Directory the units are written to.
+    pub dstdir: PathBuf,
+    /// Directory the scripts of commands are written to, instead of next to the
+    /// units, when those go into a directory that is only for units.
+    pub script_dir: Option<PathBuf>,
+    /// Translate everything, but don't write anything.
+    pub dry_run: bool,
+    /// The generator runs again on a daemon reload, after boot, so `@reboot` jobs,
+    /// which already ran, are skipped. Only the generator itself sets it.
+    pub reloading: bool,
+    /// Environment every crontab starts with, before its own variable settings.
+    pub defaults: BTreeMap<String, String>,
+    /// Prefix of the generated unit names.
+    pub namespace: String,
+    /// Directory all scanned and written paths are relative to, for a chroot or an image.
+    pub root: PathBuf,
+    /// User spool directories to look for crontabs in, in order.
+    pub spool_dirs: Vec<PathBuf>,
+    /// Ids of the units generated so far, shared by all clones of the configuration.
+    pub unit_ids: Arc<Mutex<BTreeSet<String>>>,
+    /// User crontabs are run as, instead of the owner of their file, for the ones
+    /// that aren't read from a file.
+    pub owner: Option<u32>,
+    /// Generator directory for units that take precedence over all others, where
+    /// the units of `EARLY` jobs go, instead of `dstdir`.
+    pub early_dir: Option<PathBuf>,
+    /// Whether jobs that don't set `PERSISTENT` (or set it to auto) are persistent,
+    /// instead of only the ones with a period like `@daily`.
+    pub default_persistent: Option<bool>,
+    /// Version of systemd the units are for, if it is an older one than the
+    /// running system's, so directives and syntax it lacks are left out.
+    pub compat: Option<u32>,
+    /// Write the units of each crontab into a subdirectory named after it, linked
+    /// from the output directory, instead of right into the output directory.
+    pub by_source: bool,
+    /// Unit aliases set with `NAME` so far, with the services they stand for,
+    /// shared like `unit_ids`.
+    pub aliases: Arc<Mutex<BTreeMap<String, String>>>,
+    /// Jobs named by other jobs so far, for `resolve_dependencies`, shared like `unit_ids`.
+    pub dependencies: Arc<Mutex<Vec<Dependency>>>,
+}
+
+impl GeneratorConfig {
+    pub fn new<P: Into<PathBuf>>(dstdir: P) -> GeneratorConfig {
+        GeneratorConfig {
+            dstdir: dstdir.into(),
+            script_dir: None,
+            dry_run: false,
+            reloading: false,
+            defaults: BTreeMap::new(),
+            namespace: "cron".to_owned(),
+            root: PathBuf::from("/"),
+            spool_dirs: [USERS_CRONTAB_DIR, "/var/spool/cron/crontabs", "/var/spool/cron"].iter().map(PathBuf::from).collect(),
+            unit_ids: Arc::new(Mutex::new(BTreeSet::new())),
+            owner: None,
+            early_dir: None,
+            default_persistent: None,
+            compat: None,
+            by_source: false,
+            aliases: Arc::new(Mutex::new(BTreeMap::new())),
+            dependencies: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    pub fn root<P: Into<PathBuf>>(mut self, root: P) -> GeneratorConfig {
+        self.root = root.into();
+        self
+    }
+
+    /// Moves an absolute path of the running system below the configured root.
+    pub fn rooted<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let path = path.as_ref();
+        self.root.join(path.strip_prefix("/").unwrap_or(path))
+    }
+
+    /// The path a path below the configured root has on the system there, the
+    /// way units refer to it: `<root>/etc/crontab` is `/etc/crontab`.
+    pub fn unrooted<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        let path = path.as_ref();
+        path.strip_prefix(&self.root).map(|relative| Path::new("/").join(relative)).unwrap_or_else(|_| path.to_owned())
+    }
+
+    /// Looks up a user of the system below the configured root, in its passwd file.
+    pub fn user_by_name(&self, name: &str) -> Option<PasswdEntry> {
+        let passwd = self.rooted("/etc/passwd");
+        // the lookup panics on a missing file, as there is none in a bare image
+        metadata(&passwd).ok().and_then(|_| get_entry_by_name_from_path(&passwd, name))
+    }
+
+    /// Looks up a user by uid, like `user_by_name`.
+    pub fn user_by_uid(&self, uid: u32) -> Option<PasswdEntry> {
+        let passwd = self.rooted("/etc/passwd");
+        metadata(&passwd).ok().and_then(|_| get_entry_by_uid_from_path(&passwd, uid))
+    }
+
+    pub fn dry_run(mut self, dry_run: bool) -> GeneratorConfig {
+        self.dry_run = dry_run;
+        self
+    }
+
+    pub fn reloading(mut self, reloading: bool) -> GeneratorConfig {
+        self.reloading = reloading;
+        self
+    }
+
+    pub fn by_source(mut self, by_source: bool) -> GeneratorConfig {
+        self.by_source = by_source;
+        self
+    }
+
+    pub fn compat(mut self, compat: Option<u32>) -> GeneratorConfig {
+        self.compat = compat;
+        self
+    }
+}
+
+/// A crontab entry that was translated into units.
+#[derive(Debug)]
+pub struct Job {
+    pub path: PathBuf,
+    pub lineno: usize,
+    pub line: String,
+    /// `OnCalendar=` expressions of the timer, empty for boot timers.
+    pub calendars: Vec<String>,
+    /// Files written for the entry, relative to the output directory.
+    pub files: Vec<PathBuf>,
+    /// In a dry run, the names and contents of the units and script that would
+    /// have been written for the entry.
+    pub units: Vec<(String, String)>,
+    /// How each calendar field of the entry went into `OnCalendar=`, see
+    /// `calendar_fields`, empty for periods like `@daily`.
+    pub fields: Vec<(&'static str, String)>,
+    /// Variables set above the entry that affect when it runs.
+    pub schedule_vars: BTreeMap<String, String>,
+}
+
+/// Something in a crontab that was worked around or left out, like an ignored
+/// variable or an unparsable line.
+#[derive(Debug)]
+pub struct Warning {
+    pub path: PathBuf,
+    /// Line the warning is about, if it isn't about the whole file.
+    pub lineno: Option<usize>,
+    /// Column, counted from 1, of the field a line that didn't parse went wrong in.
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.lineno, self.column) {
+            (Some(lineno), Some(column)) => write!(f, "{}:{}:{}: {}", self.path.display(), lineno, column, self.message),
+            (Some(lineno), None) => write!(f, "{}:{}: {}", self.path.display(), lineno, self.message),
+            _ => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+/// Tally of what a processing run made of the crontabs it was given.
+#[derive(Debug, Default)]
+pub struct Summary {
+    pub files: usize,
+    pub translated: usize,
+    /// Entries not turned into units, errors included, with the reason why.
+    pub skipped: Vec<(PathBuf, String)>,
+    pub errors: usize,
+    pub env_vars: BTreeSet<String>,
+    pub jobs: Vec<Job>,
+    pub warnings: Vec<Warning>,
+}
+
+impl Summary {
+    pub fn merge(&mut self, other: Summary) {
+        self.files += other.files;
+        self.translated += other.translated;
+        self.skipped.extend(other.skipped);
+        self.errors += other.errors;
+        self.env_vars.extend(other.env_vars);
+        self.jobs.extend(other.jobs);
+        self.warnings.extend(other.warnings);
+    }
+
+    /// Logs a warning and keeps it for the caller.
+    fn warn(&mut self, path: &Path, lineno: Option<usize>, message: String) {
+        self.push_warning(Warning { path: path.to_owned(), lineno, column: None, message });
+    }
+
+    fn push_warning(&mut self, warning: Warning) {
+        warn!("{}", warning);
+        self.warnings.push(warning);
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "files={} jobs={} skipped={} errors={}", self.files, self.translated, self.skipped.len(), self.errors)
+    }
+}
+
+pub fn process_crontab_dir<T: FromStr + 'static, P: AsRef<Path>>(srcdir: P, config: &GeneratorConfig) -> Summary
     where CrontabEntry: From<T>,
           CrontabFileError: From<<T as FromStr>::Err>
 {
-    let files = read_dir(srcdir).and_then(|fs| {
+    let mut summary = Summary::default();
+    let srcdir = srcdir.as_ref();
+    match crontab_files(srcdir) {
+        Err(err) => warn!("error processing directory {}: {}", srcdir.display(), err),
+        Ok(files) => {
+            for file in files {
+                summary.merge(process_crontab_file::<T, _>(file, config));
+            }
+        }
+    }
+    summary
+}
+
+/// Processes the user crontabs of every configured spool directory that exists,
+/// each directory only once even if several of the paths lead to it. Returns
+/// `None` if there is none, e.g. because /var isn't mounted yet.
+pub fn process_spool_dirs(config: &GeneratorConfig) -> Option<Summary> {
+    let mut seen = BTreeSet::new();
+    let mut summary = None;
+    for dir in config.spool_dirs.iter().map(|dir| config.rooted(dir)) {
+        match metadata(&dir) {
+            Ok(ref meta) if meta.is_dir() && seen.insert((meta.dev(), meta.ino())) => {
+                summary.get_or_insert_with(Summary::default).merge(process_user_crontab_dir(&dir, config));
+            }
+            _ => (),
+        }
+    }
+    summary
+}
+
+/// Processes a spool directory of user crontabs, which are named after their
+/// users. Anything else in there, like an editor's backup copy, is skipped.
+pub fn process_user_crontab_dir<P: AsRef<Path>>(srcdir: P, config: &GeneratorConfig) -> Summary {
+    let mut summary = Summary::default();
+    let srcdir = srcdir.as_ref();
+    match crontab_files(srcdir) {
+        Err(err) => warn!("error processing directory {}: {}", srcdir.display(), err),
+        Ok(files) => {
+            for file in files {
+                if file.file_name().and_then(|n| n.to_str()).and_then(|name| config.user_by_name(name)).is_some() {
+                    summary.merge(process_crontab_file::<UserCrontabEntry, _>(file, config));
+                } else {
+                    summary.warn(&file, None, "skipping the file, it isn't named after a user".to_owned());
+                    summary.skipped.push((file, "not named after a user".to_owned()));
+                }
+            }
+        }
+    }
+    summary
+}
+
+/// Lists the regular, non-hidden files of a crontab directory. Subdirectories
+/// are left out rather than descended into, like cron does, so neither deep nor
+/// cyclic trees below a scanned directory are ever walked.
+fn crontab_files(srcdir: &Path) -> io::Result<Vec<PathBuf>> {
+    read_dir(srcdir).and_then(|fs| {
         fs.map(|r| r.map(|p| p.path()))
           .filter(|r| {
               r.as_ref()
@@ -24,46 +297,1232 @@ pub fn process_crontab_dir<T: FromStr, D: AsRef<Path>>(srcdir: &str, dstdir: D)
                })
                .unwrap_or(true)
           })
-          .collect::<Result<Vec<PathBuf>, _>>()
-    });
-    match files {
-        Err(err) => warn!("error processing directory {}: {}", srcdir, err),
-        Ok(files) => {
-            for file in files {
-                process_crontab_file::<T, _, _>(file, dstdir.as_ref());
+          .collect()
+    })
+}
+
+pub fn process_crontab_file<T: FromStr + 'static, P: AsRef<Path>>(path: P, config: &GeneratorConfig) -> Summary
+    where CrontabEntry: From<T>,
+          CrontabFileError: From<<T as FromStr>::Err>
+{
+    let mut summary = Summary::default();
+    let path = path.as_ref();
+
+    let crontab = match read_crontab(path) {
+        Ok((crontab, lossy)) => {
+            if lossy {
+                summary.warn(path, None, "the file isn't valid UTF-8, the invalid bytes are replaced".to_owned());
             }
+            crontab
         }
-    }
+        Err(err) => {
+            warn!("error parsing file {}: {}", path.display(), err);
+            if err.kind() != io::ErrorKind::NotFound {
+                summary.errors += 1;
+            }
+            return summary;
+        }
+    };
+
+    summary.merge(process_crontab_str::<T, _>(&crontab, path, config));
+    summary
 }
 
-pub fn process_crontab_file<T: FromStr, P: AsRef<Path>, D: AsRef<Path>>(path: P, dstdir: D)
+/// Processes a crontab already read from `path`, or one that isn't in a file at
+/// all, given a path to refer to it by with an `owner` in the configuration.
+pub fn process_crontab_str<T: FromStr + 'static, P: AsRef<Path>>(crontab: &str, path: P, config: &GeneratorConfig) -> Summary
     where CrontabEntry: From<T>,
           CrontabFileError: From<<T as FromStr>::Err>
 {
-    CrontabFile::<T>::new(path.as_ref())
-        .map(|crontab| {
-            let mut env = BTreeMap::new();
-            for entry in crontab {
-                match entry {
-                    Ok(CrontabEntry::EnvVar(EnvVarEntry(name, value))) => {
-                        env.insert(name, value);
-                    }
-                    Ok(data) => {
-                        match generate_systemd_units(data, &env, path.as_ref(), dstdir.as_ref()) {
-                            Ok(_) => (),
-                            Err(err) => warn!("error generating unit from {}: {}", path.as_ref().display(), err),
-                        }
+    let mut summary = Summary::default();
+    let path = path.as_ref();
+    let has_calendar = has_calendar_fields::<T>();
+
+    summary.files += 1;
+    let mut env = config.defaults.clone();
+    let mut set_at = BTreeMap::new();
+    let mut disabled = false;
+    let mut has_entries = false;
+    let mut comment = None;
+    for (lineno, line) in crontab.lines().enumerate().map(|(n, l)| (n + 1, l)) {
+        // indentation is allowed, but the entry parsers expect the first field right away
+        let indent = line.len() - line.trim_start().len();
+        let line = line.trim_start();
+        if let Some(text) = line.strip_prefix('#') {
+            // the comment right above a job describes it, unless it is a job commented
+            // out, or the column headings crontab -e starts a new crontab with
+            comment = None;
+            match text.trim() {
+                "DISABLE" => disabled = true,
+                "ENABLE" => disabled = false,
+                "" => (),
+                text if !is_column_heading(text) && parse_crontab_line::<T>(text).is_err() => comment = Some(text),
+                _ => (),
+            }
+            continue;
+        }
+        let comment = comment.take();
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let source = line;
+
+        let line = match normalize_env_assignment(line) {
+            Some(normalized) => Cow::Owned(normalized),
+            None => Cow::Borrowed(line),
+        };
+
+        let line = match normalize_period(&line) {
+            Some(normalized) => Cow::Owned(normalized),
+            None => line,
+        };
+
+        // @shutdown isn't a period cron knows, its jobs are @reboot ones run at the other end
+        let (line, at_shutdown) = match shutdown_as_reboot(&line) {
+            Some(rewritten) => (Cow::Owned(rewritten), true),
+            None => (line, false),
+        };
+
+        // the calendar fields are rewritten only where there are some, the
+        // fields of an anacrontab line are the period, delay, job id and command
+        let line = match any_day_placeholder(&line) {
+            Some(rewritten) if has_calendar => Cow::Owned(rewritten),
+            _ => line,
+        };
+
+        let sunday_first = env.get("WEEK_START").map(|v| v.trim().eq_ignore_ascii_case("sunday")).unwrap_or(false);
+        let shifted = if sunday_first && has_calendar { shift_weekdays(&line) } else { None };
+        let line = shifted.map_or(line, Cow::Owned);
+
+        let line = match expand_wrapping_weekdays(&line) {
+            Some(expanded) if has_calendar => Cow::Owned(expanded),
+            _ => line,
+        };
+
+        let line = match expand_nth_weekday(&line) {
+            Some(expanded) if has_calendar => {
+                summary.warn(path, Some(lineno), format!("the nth weekday extension is approximated as \"{}\"", expanded));
+                Cow::Owned(expanded)
+            }
+            _ => line,
+        };
+
+        // a step of 0 never gets anywhere
+        if has_calendar && has_zero_step(&line) {
+            summary.warn(path, Some(lineno), "skipping the line, a step of 0 is invalid".to_owned());
+            summary.skipped.push((path.to_owned(), format!("line {}: step of 0", lineno)));
+            summary.errors += 1;
+            continue;
+        }
+
+        // rather than a parsing error, or a schedule that looks right and isn't
+        if let Some(construct) = unsupported_extension(&line).filter(|_| has_calendar) {
+            summary.warn(path, Some(lineno), format!("skipping the line, the {} extension is not supported", construct));
+            summary.skipped.push((path.to_owned(), format!("line {}: unsupported {}", lineno, construct)));
+            summary.errors += 1;
+            continue;
+        }
+
+        match parse_crontab_line::<T>(&line) {
+            Ok(CrontabEntry::EnvVar(EnvVarEntry(name, _))) => {
+                // the parser strips quotes too, but takes a value that only starts
+                // with a single quote for a quoted one, so it is unquoted once, from the line
+                let value = unquote(line.split_once('=').map_or("", |(_, value)| value)).to_owned();
+                if name == "WEEK_START" && !["sunday", "monday"].contains(&&*value.to_lowercase()) {
+                    summary.warn(path, Some(lineno), format!("ignoring invalid WEEK_START: \"{}\"", value));
+                }
+                summary.env_vars.insert(name.clone());
+                // setting a variable again is how it's changed for the jobs below, but
+                // when a job gets the wrong value, it helps to know where the old one was set
+                if let Some(previous) = set_at.insert(name.clone(), lineno) {
+                    if env.get(&name) != Some(&value) {
+                        summary.warn(path, Some(lineno), format!("{} is set again, the jobs between it and line {} use \"{}\"",
+                                                                 name, previous, env.get(&name).map(|v| &**v).unwrap_or("")));
                     }
-                    Err(err @ CrontabFileError { kind: CrontabFileErrorKind::Io(_), .. }) => {
-                        warn!("error accessing file {}: {}", path.as_ref().display(), err)
+                }
+                env.insert(name, value);
+            }
+            Ok(_) if disabled => {
+                has_entries = true;
+                info!("line {} of {} is in a disabled block, not generating units", lineno, path.display());
+            }
+            Ok(data) => {
+                has_entries = true;
+                if let Some(reason) = format_mismatch(&data, &env, config) {
+                    summary.warn(path, Some(lineno), format!("the line looks like it is in the wrong crontab format: {}", reason));
+                }
+
+                // entries are generated as soon as they are read, so `env` holds exactly
+                // the variables set above this line, as cron scopes them
+                let mut warnings = Vec::new();
+                let fields = data.calendar().map(calendar_fields).unwrap_or_default();
+                let entry_source = EntrySource {
+                    path,
+                    lineno,
+                    line: source,
+                    at_shutdown,
+                    comment,
+                };
+                let outcome = generate_systemd_units(data, &env, &entry_source, config, &mut warnings);
+                for message in warnings {
+                    summary.warn(path, Some(lineno), message);
+                }
+                match outcome {
+                    Ok(Outcome::Generated(calendars, files, units)) => {
+                        summary.translated += 1;
+                        summary.jobs.push(Job {
+                            path: path.to_owned(),
+                            lineno,
+                            line: source.to_owned(),
+                            calendars,
+                            files,
+                            units,
+                            fields,
+                            schedule_vars: env.iter()
+                                              .filter(|&(name, _)| SCHEDULE_VARS.contains(&&**name))
+                                              .map(|(name, value)| (name.clone(), value.clone()))
+                                              .collect(),
+                        });
                     }
-                    Err(err @ CrontabFileError { kind: CrontabFileErrorKind::Parse(_), .. }) => {
-                        warn!("skipping file {} due to parsing error: {}", path.as_ref().display(), err)
+                    Ok(Outcome::Skipped(reason)) => summary.skipped.push((path.to_owned(), reason)),
+                    Err(err) => {
+                        summary.warn(path, Some(lineno), format!("error generating units: {}", err));
+                        summary.skipped.push((path.to_owned(), err.to_string()));
+                        summary.errors += 1;
                     }
                 }
             }
-        })
-        .unwrap_or_else(|err| {
-            warn!("error parsing file {}: {}", path.as_ref().display(), err);
-        });
+            Err(err) => {
+                summary.push_warning(Warning {
+                    path: path.to_owned(),
+                    lineno: Some(lineno),
+                    column: Some(indent + error_column(source)),
+                    message: format!("skipping the line due to parsing error: {}", err),
+                });
+                summary.skipped.push((path.to_owned(), format!("line {}: {}", lineno, err)));
+                summary.errors += 1;
+            }
+        }
+    }
+
+    // variables alone don't make units, nor anything else to clean up
+    if !has_entries {
+        debug!("{} has no jobs, no units generated", path.display());
+    }
+
+    summary
+}
+
+/// Adds the jobs named in `REQUIRES`, `AFTER` and `CONFLICTS` to the services of
+/// the jobs that name them, as drop-ins, once all the crontabs of the run are
+/// processed and every `NAME` is known. Names no job has are warned about.
+pub fn resolve_dependencies(summary: &mut Summary, config: &GeneratorConfig) {
+    let dependencies = config.dependencies.lock().unwrap().drain(..).collect::<Vec<_>>();
+    let aliases = config.aliases.lock().unwrap().clone();
+
+    let mut directives = BTreeMap::new();
+    for dependency in dependencies {
+        let alias = if dependency.name.ends_with(".service") {
+            dependency.name.clone()
+        } else {
+            format!("{}.service", dependency.name)
+        };
+        let message = match aliases.get(&alias) {
+            Some(unit) if *unit != dependency.unit => {
+                let (_, lines) = directives.entry((dependency.path.clone(), dependency.lineno))
+                                           .or_insert_with(|| (dependency.clone(), Vec::new()));
+                for directive in dependency.directives() {
+                    lines.push(format!("{}={}", directive, unit));
+                }
+                continue;
+            }
+            Some(_) => format!("ignoring {}, the job can't depend on itself: \"{}\"", dependency.var, dependency.name),
+            None => format!("ignoring {}, no job is named \"{}\"", dependency.var, dependency.name),
+        };
+        summary.warn(&dependency.path, Some(dependency.lineno), message);
+    }
+
+    for ((path, lineno), (dependency, lines)) in directives {
+        // only the jobs whose units were generated get a drop-in
+        let job = match summary.jobs.iter_mut().find(|job| job.path == path && job.lineno == lineno) {
+            Some(job) => job,
+            None => continue,
+        };
+        match write_dependencies(&dependency.dstdir, &dependency.unit, &lines, config.dry_run) {
+            Ok((drop_in, contents)) => {
+                if config.dry_run {
+                    job.units.push((drop_in.display().to_string(), contents));
+                } else if dependency.dstdir != config.dstdir {
+                    job.files.push(dependency.dstdir.join(drop_in));
+                } else {
+                    job.files.push(drop_in);
+                }
+            }
+            Err(err) => {
+                summary.warn(&path, Some(lineno), format!("error writing the dependencies of the job: {}", err));
+                summary.errors += 1;
+            }
+        }
+    }
+}
+
+/// Checks units can be written into the output directory, creating it if needed,
+/// so a read-only one fails the run once, rather than every job on its own.
+pub fn check_dstdir(config: &GeneratorConfig) -> io::Result<()> {
+    if config.dry_run {
+        return Ok(());
+    }
+    let probe_path = config.dstdir.join(format!(".{}.tmp", MANIFEST_FILE));
+    create_dir_all(&config.dstdir)?;
+    File::create(&probe_path)?;
+    remove_file(probe_path)
+}
+
+/// The formats crontabs come in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrontabKind {
+    /// A user crontab, without a user column.
+    User,
+    /// /etc/crontab or a file in /etc/cron.d, with a user column.
+    System,
+    /// /etc/anacrontab.
+    Anacron,
+}
+
+/// Why a crontab given as a string couldn't be translated completely: the
+/// number of errors, and the warnings, the errors among them.
+#[derive(Debug)]
+pub struct GenError {
+    pub errors: usize,
+    pub warnings: Vec<Warning>,
+}
+
+impl fmt::Display for GenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.warnings.first() {
+            Some(warning) => write!(f, "{} errors, first warning: {}", self.errors, warning),
+            None => write!(f, "{} errors", self.errors),
+        }
+    }
+}
+
+impl Error for GenError {
+    fn description(&self) -> &str {
+        "crontab translation error"
+    }
+}
+
+/// Translates a whole crontab from a string rather than a file, returning the
+/// names and contents of the units and scripts it makes, without writing them.
+/// The jobs of a user crontab run as the calling user.
+///
+/// ```
+/// use systemd_crontab_generator::process::{parse_and_generate, CrontabKind};
+///
+/// let units = parse_and_generate("@reboot /bin/true\n0 5 * * * /bin/true\n", CrontabKind::User).unwrap();
+/// let timers = units.iter().filter(|(name, _)| name.ends_with(".timer")).map(|(_, unit)| unit).collect::<Vec<_>>();
+/// assert_eq!(timers.len(), 2);
+/// assert!(timers.iter().any(|unit| unit.contains("OnBootSec=")));
+/// assert!(timers.iter().any(|unit| unit.contains("OnCalendar=*-*-* 05:00:00")));
+///
+/// assert!(parse_and_generate("0 5 xx * * /bin/true\n", CrontabKind::User).is_err());
+/// ```
+pub fn parse_and_generate(crontab_body: &str, kind: CrontabKind) -> Result<Vec<(String, String)>, GenError> {
+    let config = GeneratorConfig { owner: Some(unsafe { getuid() }), ..GeneratorConfig::new("-").dry_run(true) };
+    let mut summary = match kind {
+        CrontabKind::User => process_crontab_str::<UserCrontabEntry, _>(crontab_body, "-", &config),
+        CrontabKind::System => process_crontab_str::<SystemCrontabEntry, _>(crontab_body, "-", &config),
+        CrontabKind::Anacron => process_crontab_str::<AnacrontabEntry, _>(crontab_body, "-", &config),
+    };
+    resolve_dependencies(&mut summary, &config);
+    if summary.errors > 0 {
+        return Err(GenError { errors: summary.errors, warnings: summary.warnings });
+    }
+    Ok(summary.jobs.into_iter().flat_map(|job| job.units).collect())
+}
+
+/// Removes the files the previous run listed in its manifest that this run didn't
+/// write again, so units of removed crontab lines go away. Only files named after
+/// the configured namespace are touched, whatever else the manifest says.
+pub fn remove_stale_files(summary: &Summary, config: &GeneratorConfig) -> io::Result<usize> {
+    let mut previous = String::new();
+    match File::open(config.dstdir.join(MANIFEST_FILE)).and_then(|mut file| file.read_to_string(&mut previous)) {
+        Ok(_) => (),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(err) => return Err(err),
+    }
+
+    let current = summary.jobs.iter().flat_map(|job| job.files.iter()).collect::<BTreeSet<_>>();
+    let prefix = format!("{}-", config.namespace);
+    let mut removed = 0;
+    for file in previous.lines().map(Path::new) {
+        let path = config.dstdir.join(file);
+        if !(is_generated_file(file, &prefix) || is_alias_link(&path, &prefix)) || current.contains(&file.to_path_buf()) {
+            continue;
+        }
+
+        match remove_file(&path) {
+            Ok(()) => {
+                removed += 1;
+                if file.parent().map(|dir| dir != Path::new("")).unwrap_or(false) {
+                    // a drop-in or source directory only goes if nothing else was put in there
+                    let _ = remove_dir(path.parent().unwrap());
+                }
+            }
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => (),
+            Err(err) => warn!("error removing stale file {}: {}", path.display(), err),
+        }
+    }
+    Ok(removed)
+}
+
+/// Whether a manifest entry has the shape of a file written for a job: a unit or
+/// script, its drop-in, its link in cron.target.wants or shutdown.target.wants,
+/// or the unit or script in the directory of its crontab.
+fn is_generated_file(file: &Path, prefix: &str) -> bool {
+    let parts = file.iter().map(|c| c.to_str().unwrap_or("")).collect::<Vec<_>>();
+    match &parts[..] {
+        [name] => name.starts_with(prefix),
+        ["cron.target.wants", name] | ["shutdown.target.wants", name] => name.starts_with(prefix),
+        [dir, "override.conf"] | [dir, "dependencies.conf"] => dir.starts_with(prefix) && dir.ends_with(".d"),
+        [dir, name] => !dir.starts_with('.') && name.starts_with(prefix),
+        _ => false,
+    }
+}
+
+/// Whether a file is the symlink a `NAME` alias is made of, pointing at a generated unit.
+fn is_alias_link(path: &Path, prefix: &str) -> bool {
+    read_link(path).ok()
+                   .and_then(|target| target.file_name().and_then(|n| n.to_str()).map(|n| n.starts_with(prefix)))
+                   .unwrap_or(false)
+}
+
+/// Lists the files written in a run in `cron-generated.manifest`, in the output
+/// directory. The previous manifest is replaced atomically, so cleanup tools
+/// always see a complete one.
+pub fn write_manifest(summary: &Summary, config: &GeneratorConfig) -> io::Result<()> {
+    let mut files = summary.jobs.iter().flat_map(|job| job.files.iter()).collect::<Vec<_>>();
+    files.sort();
+
+    let manifest_path = config.dstdir.join(MANIFEST_FILE);
+    let tmp_path = config.dstdir.join(format!(".{}.tmp", MANIFEST_FILE));
+    {
+        let mut manifest = File::create(&tmp_path)?;
+        for file in files {
+            writeln!(manifest, "{}", file.display())?;
+        }
+        manifest.sync_all()?;
+    }
+    rename(tmp_path, manifest_path)
+}
+
+/// Reads a crontab, along with whether it had to be decoded lossily because it
+/// isn't valid UTF-8, like a Latin-1 file: only the odd bytes, usually in comments
+/// or commands, are replaced, the schedules are ASCII anyway.
+fn read_crontab(path: &Path) -> io::Result<(String, bool)> {
+    let mut bytes = Vec::new();
+    File::open(path).and_then(|mut file| file.read_to_end(&mut bytes))?;
+    let (mut crontab, lossy) = match String::from_utf8(bytes) {
+        Ok(crontab) => (crontab, false),
+        Err(err) => (String::from_utf8_lossy(err.as_bytes()).into_owned(), true),
+    };
+
+    // editors on some platforms prepend a byte-order mark, which would
+    // otherwise end up glued to the first variable name or schedule field
+    if crontab.starts_with('\u{feff}') {
+        crontab.remove(0);
+    }
+
+    Ok((crontab, lossy))
+}
+
+fn parse_crontab_line<T: FromStr>(line: &str) -> Result<CrontabEntry, CrontabFileError>
+    where CrontabEntry: From<T>,
+          CrontabFileError: From<<T as FromStr>::Err>
+{
+    line.parse::<T>()
+        .map(CrontabEntry::from)
+        .or_else(|err| line.parse::<EnvVarEntry>().map(CrontabEntry::EnvVar).map_err(|_| CrontabFileError::from(err)))
+}
+
+/// Spots entries that were likely written for the other crontab format, with a
+/// user column in a user crontab, or without one in a system crontab; either
+/// way the command or the user the job runs as would be wrong.
+fn format_mismatch(entry: &CrontabEntry, env: &BTreeMap<String, String>, config: &GeneratorConfig) -> Option<String> {
+    match *entry {
+        CrontabEntry::User(_) => {
+            let word = entry.command().and_then(|cmd| cmd.split_whitespace().next()).unwrap_or("");
+            let path = env.get("PATH").map(|v| &**v).unwrap_or("/usr/bin:/bin");
+            if word.contains('/') || config.user_by_name(word).is_none() ||
+               path.split(':').any(|dir| metadata(Path::new(dir).join(word)).is_ok()) {
+                return None;
+            }
+            Some(format!("the command starts with the user name \"{}\", but user crontabs have no user column", word))
+        }
+        CrontabEntry::System(_) => {
+            entry.user()
+                 .filter(|&user| config.user_by_name(user).is_none())
+                 .map(|user| format!("\"{}\" in the user column is not a known user, is the column missing?", user))
+        }
+        _ => None,
+    }
+}
+
+/// Whether a comment names the columns of a crontab, like `m h dom mon dow command`.
+fn is_column_heading(comment: &str) -> bool {
+    static COLUMNS: [&str; 14] = ["m", "h", "min", "minute", "hour", "dom", "mon", "month", "dow", "user", "command",
+                                  "period", "delay", "job-identifier"];
+    comment.split_whitespace().all(|word| COLUMNS.contains(&&*word.to_lowercase()))
+}
+
+/// Guesses the column, counted from 1, of the field an entry that didn't parse
+/// went wrong in: the first schedule field with characters no schedule has, the
+/// end of the line if fields are missing, or the start of it.
+fn error_column(line: &str) -> usize {
+    let mut fields = Vec::new();
+    let mut start = None;
+    for (i, c) in line.char_indices().chain(Some((line.len(), ' '))) {
+        match (start, c.is_whitespace()) {
+            (None, false) => start = Some(i),
+            (Some(s), true) => {
+                fields.push((s, &line[s..i]));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+
+    let count = if line.starts_with('@') { 1 } else { 5 };
+    if fields.len() <= count {
+        return line.len() + 1;
+    }
+    fields.iter()
+          .take(count)
+          .find(|&&(_, field)| !field.chars().all(|c| c.is_ascii_alphanumeric() || "*,-/@".contains(c)))
+          .map(|&(offset, _)| offset + 1)
+          .unwrap_or(1)
+}
+
+/// Strips the matching single or double quotes a variable's value may be put in
+/// to keep its leading or trailing blanks.
+fn unquote(value: &str) -> &str {
+    let value = value.trim();
+    match value.chars().next() {
+        Some(quote @ '"') | Some(quote @ '\'') if value.len() >= 2 && value.ends_with(quote) => &value[1..value.len() - 1],
+        _ => value,
+    }
+}
+
+/// Whether the entries of a crontab format start with the five calendar fields,
+/// as user and system crontab entries do, and anacrontab ones don't.
+fn has_calendar_fields<T: 'static>() -> bool {
+    TypeId::of::<T>() != TypeId::of::<AnacrontabEntry>()
+}
+
+/// Splits off the first `n` whitespace-separated fields of a line, returning
+/// them along with the untouched remainder (usually the command).
+fn split_fields(line: &str, n: usize) -> Option<(Vec<&str>, &str)> {
+    let mut fields = Vec::with_capacity(n);
+    let mut rest = line.trim_start();
+    while fields.len() < n {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    Some((fields, rest))
+}
+
+/// Rewrites the variable settings shells would take, `export NAME=value` and
+/// `NAME = value`, to the plain `NAME=value` the parser expects. Entries can't
+/// be mistaken for them, as no schedule starts with a letter or underscore.
+fn normalize_env_assignment(line: &str) -> Option<String> {
+    let rest = if line.starts_with("export") && line[6..].starts_with(char::is_whitespace) {
+        line[6..].trim_start()
+    } else {
+        line
+    };
+
+    let name_len = rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_').unwrap_or(rest.len());
+    let name = &rest[..name_len];
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let value = match rest[name_len..].trim_start() {
+        value if value.starts_with('=') => value[1..].trim_start(),
+        _ => return None,
+    };
+
+    let normalized = format!("{}={}", name, value);
+    if normalized == line { None } else { Some(normalized) }
+}
+
+/// Whether a schedule field of the line steps by 0, like `*/0` or `1-5/00`.
+fn has_zero_step(line: &str) -> bool {
+    let fields = match split_fields(line, 5) {
+        Some((fields, _)) => fields,
+        None => return false,
+    };
+    !fields[0].starts_with('@') && !fields[0].contains('=') &&
+    fields.iter().flat_map(|field| field.split(',')).any(|part| {
+        let mut halves = part.splitn(2, '/');
+        match (halves.next(), halves.next()) {
+            (Some(range), Some(step)) => !range.is_empty() && !step.is_empty() && step.chars().all(|c| c == '0'),
+            _ => false,
+        }
+    })
+}
+
+/// Names the extension of other crons a line uses that can't be translated: the
+/// `@every` intervals, and the `L`, `W` and `#` of the day fields that are left
+/// once `?` and the plain `dow#n` have been rewritten.
+fn unsupported_extension(line: &str) -> Option<&'static str> {
+    if split_fields(line, 1).map(|(fields, _)| fields[0] == "@every").unwrap_or(false) {
+        return Some("@every interval");
+    }
+    let fields = match split_fields(line, 5) {
+        Some((fields, _)) => fields,
+        None => return None,
+    };
+    if fields[0].starts_with('@') || fields[0].contains('=') {
+        return None;
+    }
+
+    let days = fields[2].split(',').collect::<Vec<_>>();
+    let dows = fields[4].split(',').collect::<Vec<_>>();
+    if days.iter().any(|day| day.starts_with('L')) {
+        Some("L (last day of month)")
+    } else if days.iter().any(|day| day.ends_with('W')) {
+        Some("W (nearest weekday)")
+    } else if dows.iter().any(|dow| dow.len() > 1 && dow.ends_with('L')) {
+        Some("L (last weekday of month, see LAST_WEEKDAY)")
+    } else if dows.iter().any(|dow| dow.contains('#')) {
+        Some("# (nth weekday) along with a day of month or other weekdays")
+    } else {
+        None
+    }
+}
+
+/// Rewrites the period nickname of a line to the lowercase form the parser knows,
+/// with `@annually` as `@yearly`, so both spellings, in any case, make the same
+/// `yearly` timer.
+fn normalize_period(line: &str) -> Option<String> {
+    let (fields, command) = split_fields(line, 1)?;
+    if !fields[0].starts_with('@') {
+        return None;
+    }
+
+    let period = match &*fields[0].to_lowercase() {
+        "@annually" => "@yearly".to_owned(),
+        period => period.to_owned(),
+    };
+    if period == fields[0] { None } else { Some(format!("{} {}", period, command)) }
+}
+
+/// Rewrites a `@shutdown` line to the `@reboot` one the parser knows, the caller
+/// then has it generated as a shutdown job.
+fn shutdown_as_reboot(line: &str) -> Option<String> {
+    match split_fields(line, 1) {
+        Some((ref fields, command)) if fields[0] == "@shutdown" => Some(format!("@reboot {}", command)),
+        _ => None,
+    }
+}
+
+/// Rewrites the `?` ("no specific value") Quartz schedules take for the day of
+/// month or of week to the `*` it means here, `0 0 ? * MON` to `0 0 * * MON`.
+fn any_day_placeholder(line: &str) -> Option<String> {
+    let (mut fields, command) = split_fields(line, 5)?;
+    if fields[0].starts_with('@') || fields[0].contains('=') || (fields[2] != "?" && fields[4] != "?") {
+        return None;
+    }
+
+    for &i in [2, 4].iter() {
+        if fields[i] == "?" {
+            fields[i] = "*";
+        }
+    }
+    Some(format!("{} {}", fields.join(" "), command))
+}
+
+/// Rewrites the numeric weekdays of a line counting from Sunday as 1, as with
+/// `WEEK_START=sunday`, to cron's own numbers counting from Sunday as 0, e.g.
+/// `2-6` (Monday to Friday) to `1-5`. Weekday names and steps are kept.
+fn shift_weekdays(line: &str) -> Option<String> {
+    let (mut fields, command) = split_fields(line, 5)?;
+    if fields[0].starts_with('@') || fields[0].contains('=') || !fields[4].contains(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let shift = |value: &str| {
+        match value.parse::<u8>() {
+            Ok(day) if (1..=7).contains(&day) => (day - 1).to_string(),
+            _ => value.to_owned(),
+        }
+    };
+    let dows = fields[4].split(',')
+                        .map(|part| {
+                            // what follows a step or nth weekday isn't a weekday
+                            let end = part.find(['/', '#']).unwrap_or(part.len());
+                            let days = part[..end].split('-').map(&shift).collect::<Vec<_>>().join("-");
+                            format!("{}{}", days, &part[end..])
+                        })
+                        .collect::<Vec<_>>()
+                        .join(",");
+    fields[4] = &dows;
+    Some(format!("{} {}", fields.join(" "), command))
+}
+
+/// Rewrites the weekday ranges of a line that wrap around the end of the week,
+/// which cron takes but the parser doesn't, into the days they stand for, e.g.
+/// `fri-mon` to `5,6,0,1`.
+fn expand_wrapping_weekdays(line: &str) -> Option<String> {
+    static NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+    let (mut fields, command) = split_fields(line, 5)?;
+    if fields[0].starts_with('@') || fields[0].contains('=') {
+        return None;
+    }
+
+    // Sunday as 7 ends a range like 1-7 rather than wrapping it
+    let day = |value: &str| {
+        value.parse::<usize>()
+             .ok()
+             .filter(|&day| day <= 7)
+             .or_else(|| NAMES.iter().position(|name| name.eq_ignore_ascii_case(value)))
+    };
+    let mut wrapped = false;
+    let dows = fields[4].split(',')
+                        .map(|part| {
+                            let mut bounds = part.splitn(2, '-');
+                            match (bounds.next().and_then(&day), bounds.next().and_then(&day)) {
+                                (Some(first), Some(last)) if first > last => {
+                                    wrapped = true;
+                                    (first..7).chain(0..last + 1).map(|day| day.to_string()).collect::<Vec<_>>().join(",")
+                                }
+                                _ => part.to_owned(),
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join(",");
+    if !wrapped {
+        return None;
+    }
+    fields[4] = &dows;
+    Some(format!("{} {}", fields.join(" "), command))
+}
+
+/// Rewrites the `dow#n` ("n-th such weekday of the month") extension some crons
+/// support, which systemd can't express directly, into the weekday restricted to
+/// the days of month its n-th occurrence can fall on, e.g. `5#2` to `8-14` + `5`.
+/// Only a single weekday, a number or a name, is rewritten: in a list like
+/// `1,5#2` the other days would be restricted along with it.
+fn expand_nth_weekday(line: &str) -> Option<String> {
+    static NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+    let (mut fields, command) = split_fields(line, 5)?;
+    if fields[0].starts_with('@') || fields[0].contains('=') || fields[2] != "*" {
+        return None;
+    }
+
+    let (dow, nth) = {
+        let mut parts = fields[4].splitn(2, '#');
+        match (parts.next(), parts.next().and_then(|n| n.parse::<u8>().ok())) {
+            (Some(dow), Some(nth)) if (1..=5).contains(&nth) => (dow, nth),
+            _ => return None,
+        }
+    };
+    let single_day = dow.parse::<u8>().map(|day| day <= 7).unwrap_or(false) ||
+                     NAMES.iter().any(|name| name.eq_ignore_ascii_case(dow));
+    if !single_day {
+        return None;
+    }
+
+    let days = format!("{}-{}", nth * 7 - 6, ::std::cmp::min(nth * 7, 31));
+    fields[2] = &days;
+    fields[4] = dow;
+    Some(format!("{} {}", fields.join(" "), command))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs::{File, create_dir_all, read_dir, remove_dir_all, remove_file};
+    use std::io::{Read, Write};
+    use std::os::unix::fs::symlink;
+    use std::process;
+    use std::str::FromStr;
+
+    use cronparse::CrontabFileError;
+    use cronparse::crontab::{AnacrontabEntry, CrontabEntry, SystemCrontabEntry, UserCrontabEntry};
+    use super::{CrontabKind, GeneratorConfig, MANIFEST_FILE, check_dstdir, parse_and_generate, process_crontab_dir,
+                process_crontab_file, process_crontab_str, process_spool_dirs, process_user_crontab_dir, read_crontab,
+                remove_stale_files, write_manifest};
+
+    /// Everything generated for a crontab, the units and the scripts of their commands.
+    fn generated(crontab: &str, kind: CrontabKind) -> String {
+        parse_and_generate(crontab, kind).unwrap().into_iter().map(|(_, unit)| unit).collect()
+    }
+
+    #[test]
+    fn anacrontab_lines_keep_their_fields() {
+        let units = generated("1 5 placeholder /bin/echo ? done\n", CrontabKind::Anacron);
+        assert!(units.contains("/bin/echo ? done"), "{}", units);
+        let units = generated("WEEK_START=sunday\n1 5 weekdays /bin/echo 2-6\n", CrontabKind::Anacron);
+        assert!(units.contains("/bin/echo 2-6"), "{}", units);
+        let units = generated("1 5 wrapping /bin/echo fri-mon\n", CrontabKind::Anacron);
+        assert!(units.contains("/bin/echo fri-mon"), "{}", units);
+        let units = generated("1 5 nth /bin/echo 5#2\n", CrontabKind::Anacron);
+        assert!(units.contains("/bin/echo 5#2"), "{}", units);
+        let units = generated("1 5 steps /bin/echo */0\n", CrontabKind::Anacron);
+        assert!(units.contains("/bin/echo */0"), "{}", units);
+    }
+
+    #[test]
+    fn only_a_single_weekday_is_the_nth_of_the_month() {
+        let units = generated("0 5 * * 5#2 /bin/true\n", CrontabKind::User);
+        assert!(units.contains("OnCalendar=Fri *-*-8..14 05:00:00\n"), "{}", units);
+        let units = generated("0 5 * * fri#2 /bin/true\n", CrontabKind::User);
+        assert!(units.contains("OnCalendar=Fri *-*-8..14 05:00:00\n"), "{}", units);
+        // systemd before 236 has no ranges of numbers
+        let config = GeneratorConfig { owner: Some(0), ..GeneratorConfig::new("-").dry_run(true).compat(Some(235)) };
+        let summary = process_crontab_str::<UserCrontabEntry, _>("0 5 * * 5#5 /bin/true\n", "-", &config);
+        let timer = &summary.jobs[0].units.iter().find(|(name, _)| name.ends_with(".timer")).unwrap().1;
+        assert!(timer.contains("OnCalendar=Fri *-*-29,30,31 05:00:00\n"), "{}", timer);
+        for line in ["0 5 * * 1,5#2 /bin/true\n", "0 5 * * 1-5#2 /bin/true\n", "0 5 * * */2#2 /bin/true\n"].iter() {
+            let err = parse_and_generate(line, CrontabKind::User).unwrap_err();
+            assert!(err.warnings[0].message.contains("# (nth weekday)"), "{:?}", err);
+        }
+        let units = generated("NAME=x#1 2 * * 5#2\n0 5 * * * /bin/true\n", CrontabKind::User);
+        assert!(units.contains("OnCalendar=*-*-* 05:00:00\n"), "{}", units);
+    }
+
+    /// The `Description=` of the services generated for a crontab, in order.
+    fn descriptions(crontab: &str) -> Vec<String> {
+        parse_and_generate(crontab, CrontabKind::User).unwrap()
+                                                      .into_iter()
+                                                      .filter(|(name, _)| name.ends_with(".service"))
+                                                      .flat_map(|(_, unit)| {
+                                                          unit.lines()
+                                                              .filter(|line| line.starts_with("Description="))
+                                                              .map(str::to_owned)
+                                                              .collect::<Vec<_>>()
+                                                      })
+                                                      .collect()
+    }
+
+    #[test]
+    fn description_variable_names_the_jobs_below() {
+        assert_eq!(descriptions("0 1 * * * /bin/a\nDESCRIPTION=nightly\n0 2 * * * /bin/b\n0 3 * * * /bin/c\n"),
+                   vec!["Description=[Cron] \"0 1 * * * /bin/a\"", "Description=[Cron] nightly", "Description=[Cron] nightly"]);
+    }
+
+    #[test]
+    fn comment_above_a_job_wins_over_the_description_variable() {
+        assert_eq!(descriptions("DESCRIPTION=nightly\n# rotate the logs\n0 2 * * * /bin/b\n0 3 * * * /bin/c\n"),
+                   vec!["Description=[Cron] rotate the logs", "Description=[Cron] nightly"]);
+        // only right above, and a job commented out or column headings don't describe anything
+        assert_eq!(descriptions("# rotate the logs\n\n0 2 * * * /bin/b\n# 0 1 * * * /bin/old\n0 3 * * * /bin/c\n\
+                                 # m h  dom mon dow   command\n0 4 * * * /bin/d\n"),
+                   vec!["Description=[Cron] \"0 2 * * * /bin/b\"", "Description=[Cron] \"0 3 * * * /bin/c\"",
+                        "Description=[Cron] \"0 4 * * * /bin/d\""]);
+    }
+
+    #[test]
+    fn shutdown_jobs_are_described_by_their_own_line() {
+        assert_eq!(descriptions("@shutdown /bin/sync\n@reboot /bin/true\n"),
+                   vec!["Description=[Cron] \"@shutdown /bin/sync\"", "Description=[Cron] \"@reboot /bin/true\""]);
+    }
+
+    /// The `Environment=` lines of the only service generated for a crontab.
+    fn environment(crontab: &str) -> Vec<String> {
+        parse_and_generate(crontab, CrontabKind::User).unwrap()
+                                                      .into_iter()
+                                                      .filter(|(name, _)| name.ends_with(".service"))
+                                                      .flat_map(|(_, unit)| {
+                                                          unit.lines()
+                                                              .filter(|line| line.starts_with("Environment="))
+                                                              .map(str::to_owned)
+                                                              .collect::<Vec<_>>()
+                                                      })
+                                                      .collect()
+    }
+
+    #[test]
+    fn variable_values_are_unquoted_once() {
+        assert_eq!(environment("A=plain\nB = spaced out \n0 5 * * * /bin/true\n"),
+                   vec!["Environment=\"A=plain\"", "Environment=\"B=spaced out\""]);
+        assert_eq!(environment("A=\"'single'\"\nB='\"double\"'\nC=\" padded \"\n0 5 * * * /bin/true\n"),
+                   vec!["Environment=\"A='single'\"", "Environment=\"B=\\\"double\\\"\"", "Environment=\"C= padded \""]);
+        // only matching quotes are quotes
+        assert_eq!(environment("A='open\nB=\"\n0 5 * * * /bin/true\n"),
+                   vec!["Environment=\"A='open\"", "Environment=\"B=\\\"\""]);
+    }
+
+    #[test]
+    fn variables_set_again_are_reported() {
+        let config = GeneratorConfig { owner: Some(0), ..GeneratorConfig::new("-").dry_run(true) };
+        let crontab = "MAILTO=a\n0 5 * * * /bin/true\nMAILTO=a\nMAILTO=b\n0 6 * * * /bin/true\n";
+        let summary = process_crontab_str::<UserCrontabEntry, _>(crontab, "-", &config);
+        assert_eq!(summary.warnings.len(), 1, "{:?}", summary.warnings);
+        assert_eq!(summary.warnings[0].lineno, Some(4));
+        assert_eq!(summary.warnings[0].message, "MAILTO is set again, the jobs between it and line 3 use \"a\"");
+    }
+
+    #[test]
+    fn calendar_lines_are_still_rewritten() {
+        let units = generated("0 5 ? * 1 /bin/echo ? done\n", CrontabKind::User);
+        assert!(units.contains("/bin/echo ? done"), "{}", units);
+        let units = parse_and_generate("WEEK_START=sunday\n0 5 * * 2-6 /bin/true\n", CrontabKind::User).unwrap();
+        assert!(units.iter().any(|(_, unit)| unit.contains("OnCalendar=Mon..Fri *-*-* 05:00:00")), "{:?}", units);
+        assert!(parse_and_generate("*/0 5 * * * /bin/true\n", CrontabKind::User).is_err());
+    }
+
+    /// The units generated for `crontab`, by name, and the warnings about it.
+    fn translate<T: FromStr + 'static>(crontab: &str) -> (Vec<(String, String)>, Vec<String>)
+        where CrontabEntry: From<T>,
+              CrontabFileError: From<<T as FromStr>::Err>
+    {
+        translate_with::<T>(crontab, GeneratorConfig::new("-"))
+    }
+
+    /// Like `translate`, with the settings of `config` other than its output directory.
+    fn translate_with<T: FromStr + 'static>(crontab: &str, config: GeneratorConfig) -> (Vec<(String, String)>, Vec<String>)
+        where CrontabEntry: From<T>,
+              CrontabFileError: From<<T as FromStr>::Err>
+    {
+        let config = GeneratorConfig { owner: Some(0), ..config.dry_run(true) };
+        let summary = process_crontab_str::<T, _>(crontab, "-", &config);
+        let warnings = summary.warnings.into_iter().map(|warning| warning.message).collect();
+        (summary.jobs.into_iter().flat_map(|job| job.units).collect(), warnings)
+    }
+
+    /// The contents of the generated unit, or drop-in, whose name ends with `suffix`.
+    fn unit(units: &[(String, String)], suffix: &str) -> String {
+        units.iter().find(|(name, _)| name.ends_with(suffix)).map(|(_, unit)| unit.clone()).unwrap_or_default()
+    }
+
+    #[test]
+    fn variables_only_apply_to_the_jobs_below() {
+        let (files, _) = translate::<UserCrontabEntry>("FOO=a\n0 5 * * * /bin/true\nFOO=b\n0 6 * * * /bin/false\nBAR=c\n");
+        let service = |command: &str| {
+            files.iter().find(|(name, unit)| name.ends_with(".service") && unit.contains(command)).map(|(_, unit)| unit.clone()).unwrap()
+        };
+        let (first, second) = (service("ExecStart=/bin/true\n"), service("ExecStart=/bin/false\n"));
+        assert!(first.contains("Environment=\"FOO=a\"\n") && !first.contains("FOO=b"), "{}", first);
+        assert!(second.contains("Environment=\"FOO=b\"\n") && !second.contains("FOO=a"), "{}", second);
+        assert!(!first.contains("BAR=") && !second.contains("BAR="));
+    }
+
+    #[test]
+    fn a_leading_byte_order_mark_is_not_part_of_the_crontab() {
+        let path = env::temp_dir().join(format!("cron-process-test-{}-bom", ::std::process::id()));
+        File::create(&path).and_then(|mut file| file.write_all(b"\xef\xbb\xbf0 5 * * * /bin/true\n")).unwrap();
+        let crontab = read_crontab(&path);
+        remove_file(&path).unwrap();
+        assert_eq!(crontab.ok(), Some(("0 5 * * * /bin/true\n".to_owned(), false)));
+    }
+
+    #[test]
+    fn the_config_names_the_units_and_sets_the_first_variables() {
+        let mut config = GeneratorConfig::new("-");
+        config.namespace = "nightly".to_owned();
+        config.defaults.insert("MAILTO".to_owned(), "root".to_owned());
+        let (files, _) = translate_with::<UserCrontabEntry>("0 5 * * * /bin/true\n", config);
+        let (name, service) = files.iter().find(|(name, _)| name.ends_with(".service")).expect("no service");
+        assert!(name.starts_with("nightly-"), "{}", name);
+        assert!(service.contains("OnFailure=cron-failure@%i.service\n"), "{}", service);
+    }
+
+    #[test]
+    fn the_summary_line_counts_the_files_jobs_and_skipped_entries() {
+        let dir = env::temp_dir().join(format!("cron-process-test-{}-summary", ::std::process::id()));
+        let (crontabs, dstdir) = (dir.join("cron.d"), dir.join("out"));
+        create_dir_all(&crontabs).unwrap();
+        create_dir_all(&dstdir).unwrap();
+        File::create(crontabs.join("backup")).and_then(|mut file| file.write_all(b"0 5 * * * root /bin/true\n")).unwrap();
+        File::create(crontabs.join("report")).and_then(|mut file| file.write_all(b"every day at 5 /bin/true\n")).unwrap();
+        let summary = process_crontab_dir::<SystemCrontabEntry, _>(&crontabs, &GeneratorConfig::new(&dstdir));
+        remove_dir_all(&dir).unwrap();
+        assert_eq!(summary.to_string(), "files=2 jobs=1 skipped=1 errors=1");
+    }
+
+    #[test]
+    fn dry_runs_record_the_calendars_of_each_job() {
+        let dir = env::temp_dir().join(format!("cron-process-test-{}-preview", ::std::process::id()));
+        create_dir_all(&dir).unwrap();
+        File::create(dir.join("crontab")).and_then(|mut file| file.write_all(b"5 4 * * * /bin/true\n@reboot /bin/true\n")).unwrap();
+        let config = GeneratorConfig::new(dir.join("out")).dry_run(true);
+        let summary = process_crontab_file::<UserCrontabEntry, _>(dir.join("crontab"), &config);
+        let written = dir.join("out").exists();
+        remove_dir_all(&dir).unwrap();
+        assert!(!written);
+        assert_eq!(summary.jobs.len(), 2);
+        assert_eq!((summary.jobs[0].lineno, &summary.jobs[0].line[..]), (1, "5 4 * * * /bin/true"));
+        assert_eq!(summary.jobs[0].calendars.len(), 1);
+        assert!(summary.jobs[0].calendars[0].ends_with("4:05:00"), "{:?}", summary.jobs[0].calendars);
+        assert!(summary.jobs[1].calendars.is_empty());
+    }
+
+    #[test]
+    fn spool_files_not_named_after_a_user_are_skipped() {
+        let dir = env::temp_dir().join(format!("cron-process-test-{}-spool", ::std::process::id()));
+        let (crontabs, dstdir) = (dir.join("crontabs"), dir.join("out"));
+        create_dir_all(&crontabs).unwrap();
+        create_dir_all(&dstdir).unwrap();
+        for name in &["root", "root.bak"] {
+            File::create(crontabs.join(name)).and_then(|mut file| file.write_all(b"0 5 * * * /bin/true\n")).unwrap();
+        }
+        let summary = process_user_crontab_dir(&crontabs, &GeneratorConfig::new(&dstdir));
+        remove_dir_all(&dir).unwrap();
+        assert_eq!(summary.translated, 1);
+        assert_eq!(summary.skipped.len(), 1);
+        assert_eq!(summary.skipped[0].0, crontabs.join("root.bak"));
+    }
+
+    #[test]
+    fn the_manifest_lists_the_files_written() {
+        let dir = env::temp_dir().join(format!("cron-process-test-{}-manifest", ::std::process::id()));
+        let (path, dstdir) = (dir.join("crontab"), dir.join("out"));
+        create_dir_all(&dstdir).unwrap();
+        File::create(&path).and_then(|mut file| file.write_all(b"0 5 * * * /bin/true\n@daily /bin/false\n")).unwrap();
+        let config = GeneratorConfig::new(&dstdir);
+        write_manifest(&process_crontab_file::<UserCrontabEntry, _>(&path, &config), &config).unwrap();
+
+        let mut written = Vec::new();
+        for subdir in &["", "cron.target.wants"] {
+            for file in read_dir(dstdir.join(subdir)).unwrap() {
+                let file = file.unwrap().path();
+                if !file.is_dir() && !file.ends_with(MANIFEST_FILE) {
+                    written.push(file.strip_prefix(&dstdir).unwrap().display().to_string());
+                }
+            }
+        }
+        written.sort();
+        let mut manifest = String::new();
+        File::open(dstdir.join(MANIFEST_FILE)).and_then(|mut file| file.read_to_string(&mut manifest)).unwrap();
+        remove_dir_all(&dir).unwrap();
+        assert_eq!(written.len(), 6);
+        assert_eq!(manifest.lines().collect::<Vec<_>>(), written);
+    }
+
+    #[test]
+    fn units_of_removed_lines_go_away_on_the_next_run() {
+        let dir = env::temp_dir().join(format!("cron-process-test-{}-stale", ::std::process::id()));
+        let (path, dstdir) = (dir.join("crontab"), dir.join("out"));
+        create_dir_all(&dstdir).unwrap();
+        File::create(dstdir.join("other.service")).unwrap();
+        let units = || {
+            let mut units = read_dir(&dstdir).unwrap().map(|f| f.unwrap().file_name().into_string().unwrap()).collect::<Vec<_>>();
+            units.sort();
+            units
+        };
+
+        File::create(&path).and_then(|mut file| file.write_all(b"0 5 * * * /bin/true\n@daily /bin/false\n")).unwrap();
+        let config = GeneratorConfig::new(&dstdir);
+        let summary = process_crontab_file::<UserCrontabEntry, _>(&path, &config);
+        assert_eq!(remove_stale_files(&summary, &config).unwrap(), 0);
+        write_manifest(&summary, &config).unwrap();
+        let before = units();
+
+        // the links of the last run would be in the way of the new ones
+        remove_dir_all(dstdir.join("cron.target.wants")).unwrap();
+        File::create(&path).and_then(|mut file| file.write_all(b"0 5 * * * /bin/true\n")).unwrap();
+        let config = GeneratorConfig::new(&dstdir);
+        let summary = process_crontab_file::<UserCrontabEntry, _>(&path, &config);
+        let removed = remove_stale_files(&summary, &config).unwrap();
+        let after = units();
+        let wants = read_dir(dstdir.join("cron.target.wants")).unwrap().count();
+        remove_dir_all(&dir).unwrap();
+        assert_eq!(removed, 2);
+        assert_eq!(before.len(), after.len() + 2);
+        assert!(after.iter().all(|unit| before.contains(unit)));
+        assert!(after.contains(&"other.service".to_owned()));
+        assert_eq!(wants, 1);
+    }
+
+    #[test]
+    fn indented_lines_are_read_like_the_others() {
+        let (files, warnings) = translate::<UserCrontabEntry>("  \tFOO=bar\n    0 5 * * * /bin/true\n\t# a comment\n");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        assert_eq!(files.iter().filter(|(name, _)| name.ends_with(".timer")).count(), 1);
+        assert!(unit(&files, ".timer").contains("5:00:00\n"));
+    }
+
+    #[test]
+    fn each_spool_directory_is_processed_once() {
+        let dir = env::temp_dir().join(format!("cron-process-test-{}-spools", ::std::process::id()));
+        let (spool, dstdir) = (dir.join("crontabs"), dir.join("out"));
+        create_dir_all(&spool).unwrap();
+        create_dir_all(&dstdir).unwrap();
+        symlink(&spool, dir.join("cron")).unwrap();
+        File::create(spool.join("root")).and_then(|mut file| file.write_all(b"0 5 * * * /bin/true\n")).unwrap();
+
+        let config = GeneratorConfig { spool_dirs: vec![dir.join("missing"), dir.join("crontabs"), dir.join("cron")],
+                                       ..GeneratorConfig::new(&dstdir) };
+        let summary = process_spool_dirs(&config);
+        let none = process_spool_dirs(&GeneratorConfig { spool_dirs: vec![dir.join("missing")], ..GeneratorConfig::new(&dstdir) });
+        remove_dir_all(&dir).unwrap();
+        assert_eq!(summary.map(|s| (s.files, s.translated)), Some((1, 1)));
+        assert!(none.is_none());
+    }
+
+    #[test]
+    fn jobs_between_disable_and_enable_are_left_out() {
+        let (files, warnings) = translate::<UserCrontabEntry>("0 1 * * * /bin/first\n# DISABLE\n0 2 * * * /bin/second\n\
+                                                               0 3 * * * /bin/third\n#ENABLE\n0 4 * * * /bin/fourth\n");
+        let services = files.iter().filter(|(name, _)| name.ends_with(".service")).map(|(_, unit)| unit).collect::<Vec<_>>();
+        assert_eq!(services.len(), 2);
+        assert!(services.iter().any(|service| service.contains("/bin/first")));
+        assert!(services.iter().any(|service| service.contains("/bin/fourth")));
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn lines_in_the_other_crontab_format_are_warned_about() {
+        let (_, warnings) = translate::<UserCrontabEntry>("0 5 * * * root /usr/sbin/backup\n");
+        assert!(warnings.iter().any(|w| w.contains("wrong crontab format") && w.contains("\"root\"")), "{:?}", warnings);
+
+        let (_, warnings) = translate::<UserCrontabEntry>("0 5 * * * /usr/sbin/backup root\n");
+        assert!(warnings.iter().all(|w| !w.contains("wrong crontab format")), "{:?}", warnings);
+
+        let (_, warnings) = translate::<SystemCrontabEntry>("0 5 * * * /usr/sbin/backup\n");
+        assert!(warnings.iter().any(|w| w.contains("wrong crontab format") && w.contains("is the column missing")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn warnings_come_with_the_line_they_are_about() {
+        let dir = env::temp_dir().join(format!("cron-process-test-{}-warnings", ::std::process::id()));
+        let path = dir.join("anacrontab");
+        create_dir_all(&dir).unwrap();
+        File::create(&path).and_then(|mut file| file.write_all(b"1 0 daily /bin/true\n400 0 rarely /bin/true\n")).unwrap();
+        let summary = process_crontab_file::<AnacrontabEntry, _>(&path, &GeneratorConfig::new(dir.join("out")).dry_run(true));
+        remove_dir_all(&dir).unwrap();
+        assert_eq!(summary.warnings.len(), 1);
+        let warning = &summary.warnings[0];
+        assert_eq!((&warning.path, warning.lineno), (&path, Some(2)));
+        assert!(warning.message.starts_with("anacrontab period of 400 days is "), "{}", warning.message);
+        assert_eq!(warning.to_string(), format!("{}:2: {}", path.display(), warning.message));
+    }
+
+    #[test]
+    fn crontab_directories_are_not_descended_into() {
+        let dir = env::temp_dir().join(format!("cron-process-test-{}-nested", process::id()));
+        create_dir_all(dir.join("sub/deeper")).unwrap();
+        for file in ["jobs", "sub/jobs", "sub/deeper/jobs", ".hidden"].iter() {
+            File::create(dir.join(file)).and_then(|mut f| f.write_all(b"0 5 * * * root /bin/true\n")).unwrap();
+        }
+        symlink(&dir, dir.join("loop")).unwrap();
+
+        let config = GeneratorConfig::new("-").dry_run(true);
+        let summary = process_crontab_dir::<SystemCrontabEntry, _>(&dir, &config);
+        remove_dir_all(&dir).unwrap();
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.jobs.len(), 1);
+        assert_eq!(summary.jobs[0].path, dir.join("jobs"));
+    }
+
+    #[test]
+    fn variables_can_be_set_like_in_a_shell() {
+        let (files, warnings) = translate::<UserCrontabEntry>("export PATH=/x\nFOO = bar\nexport  BAZ =qux\n0 5 * * * /bin/true\n");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let service = unit(&files, ".service");
+        for assignment in &["PATH=/x", "FOO=bar", "BAZ=qux"] {
+            assert!(service.contains(&format!("\nEnvironment=\"{}\"\n", assignment)), "{}", service);
+        }
+    }
+
+    #[test]
+    fn steps_of_zero_skip_the_line() {
+        let (files, warnings) = translate::<UserCrontabEntry>("*/0 * * * * /bin/true\n0 1-5/00 * * * /bin/true\n0 5 * * * /bin/true\n");
+        assert_eq!(files.iter().filter(|(name, _)| name.ends_with(".timer")).count(), 1);
+        assert_eq!(warnings, ["skipping the line, a step of 0 is invalid", "skipping the line, a step of 0 is invalid"]);
+    }
+
+    #[test]
+    fn files_that_arent_utf8_are_read_lossily() {
+        let dir = env::temp_dir().join(format!("cron-process-test-{}-latin1", ::std::process::id()));
+        let path = dir.join("crontab");
+        create_dir_all(&dir).unwrap();
+        File::create(&path).and_then(|mut file| file.write_all(b"# caf\xe9\n0 5 * * * /bin/true\n")).unwrap();
+        let summary = process_crontab_file::<UserCrontabEntry, _>(&path, &GeneratorConfig::new(dir.join("out")).dry_run(true));
+        remove_dir_all(&dir).unwrap();
+        assert_eq!((summary.translated, summary.errors), (1, 0));
+        assert_eq!(summary.warnings.len(), 1);
+        assert_eq!(summary.warnings[0].message, "the file isn't valid UTF-8, the invalid bytes are replaced");
+    }
+
+    #[test]
+    fn weekday_ranges_can_wrap_around_the_week() {
+        let (files, warnings) = translate::<UserCrontabEntry>("0 5 * * fri-mon /bin/true\n");
+        assert!(warnings.is_empty(), "{:?}", warnings);
+        let timer = unit(&files, ".timer");
+        assert!(timer.contains("\nOnCalendar=Fri,Sat,Sun,Mon *-*-* 05:00:00\n"), "{}", timer);
+    }
+
+    #[test]
+    fn annually_is_the_same_as_yearly() {
+        for crontab in &["@yearly /bin/true\n", "@annually /bin/true\n", "@Annually /bin/true\n"] {
+            let (files, warnings) = translate::<UserCrontabEntry>(crontab);
+            let timer = unit(&files, ".timer");
+            assert_eq!(timer.lines().filter(|l| l.starts_with("OnCalendar=")).collect::<Vec<_>>(), ["OnCalendar=yearly"], "{}", timer);
+            assert!(warnings.is_empty(), "{:?}", warnings);
+        }
+    }
+
+    #[test]
+    fn an_unwritable_output_directory_is_found_up_front() {
+        let dir = env::temp_dir().join(format!("cron-process-test-{}-dstdir", ::std::process::id()));
+        create_dir_all(&dir).unwrap();
+        File::create(dir.join("file")).unwrap();
+        // even root can't create a directory inside a regular file
+        let unwritable = GeneratorConfig::new(dir.join("file/out"));
+        let checked = check_dstdir(&unwritable);
+        let dry_run = check_dstdir(&unwritable.clone().dry_run(true));
+        let created = check_dstdir(&GeneratorConfig::new(dir.join("out")));
+        let left = read_dir(dir.join("out")).map(|entries| entries.count());
+        remove_dir_all(&dir).unwrap();
+        assert!(checked.is_err());
+        assert!(dry_run.is_ok());
+        assert!(created.is_ok());
+        assert_eq!(left.ok(), Some(0));
+    }
+
+    #[test]
+    fn crontabs_of_variables_alone_make_no_units() {
+        let (files, warnings) = translate::<UserCrontabEntry>("SHELL=/bin/bash\nMAILTO=root\n\n# no jobs yet\n");
+        assert!(files.is_empty(), "{:?}", files);
+        assert!(warnings.is_empty(), "{:?}", warnings);
+    }
+
+    #[test]
+    fn a_question_mark_is_any_day() {
+        let calendars = |crontab: &str| {
+            let (files, warnings) = translate::<UserCrontabEntry>(crontab);
+            assert!(warnings.is_empty(), "{:?}", warnings);
+            unit(&files, ".timer").lines().filter(|l| l.starts_with("OnCalendar=")).map(str::to_owned).collect::<Vec<_>>()
+        };
+        assert_eq!(calendars("0 0 ? * MON /bin/true\n"), calendars("0 0 * * MON /bin/true\n"));
+        assert_eq!(calendars("30 4 1 * ? /bin/true\n"), calendars("30 4 1 * * /bin/true\n"));
+
+        let (_, warnings) = translate::<UserCrontabEntry>("? 0 * * * /bin/true\n");
+        assert!(warnings.iter().any(|w| w.contains("skipping the line due to parsing error")), "{:?}", warnings);
+    }
+
+    #[test]
+    fn extensions_of_other_crons_are_named_when_skipped() {
+        for &(crontab, construct) in [("@every 5m /bin/true", "the @every interval extension"),
+                                      ("0 5 L * * /bin/true", "the L (last day of month) extension"),
+                                      ("0 5 15W * * /bin/true", "the W (nearest weekday) extension"),
+                                      ("0 5 * * 5L /bin/true", "the L (last weekday of month, see LAST_WEEKDAY) extension"),
+                                      ("0 5 1 * 1#2 /bin/true", "the # (nth weekday)")]
+                                         .iter() {
+            let (files, warnings) = translate::<UserCrontabEntry>(&format!("{}\n", crontab));
+            assert!(files.is_empty(), "{:?}", files);
+            assert_eq!(warnings.len(), 1, "{:?}", warnings);
+            assert!(warnings[0].starts_with(&format!("skipping the line, {}", construct)), "{:?}", warnings);
+        }
+    }
 }
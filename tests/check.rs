@@ -0,0 +1,102 @@
+use std::env;
+use std::fs::{File, create_dir_all, remove_dir_all, remove_file};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn check(dir: &str) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_systemd-crontab-generator"))
+                     .arg("--check")
+                     .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/").to_owned() + dir)
+                     .output()
+                     .unwrap();
+    (output.status.code().unwrap(), String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[test]
+fn reboot_jobs_are_checked_on_a_booted_system() {
+    // whether /run/crond.reboot exists or not, as it does on any booted system
+    let (status, output) = check("check-reboot");
+    assert!(output.contains("entries translated: 2"), "{}", output);
+    assert!(output.contains("entries skipped: 0"), "{}", output);
+    assert_eq!(status, 0);
+}
+
+#[test]
+fn invalid_entries_fail_the_check() {
+    let (status, output) = check("check-invalid");
+    assert!(output.contains("entries translated: 1"), "{}", output);
+    assert!(output.contains("entries skipped: 1"), "{}", output);
+    assert_eq!(status, 1);
+}
+
+#[test]
+fn standard_input_is_checked_as_a_user_crontab() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_systemd-crontab-generator"))
+                        .args(["--check", "-"])
+                        .stdin(Stdio::piped())
+                        .stdout(Stdio::piped())
+                        .spawn()
+                        .unwrap();
+    child.stdin.take().unwrap().write_all(b"0 5 * * * /bin/true\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("files processed: 1\n"), "{}", stdout);
+    assert!(stdout.contains("entries translated: 1\n"), "{}", stdout);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn explain_shows_how_each_field_is_translated() {
+    let path = env::temp_dir().join(format!("cron-explain-test-{}", std::process::id()));
+    File::create(&path).and_then(|mut file| file.write_all(b"DELAY=5\n30 2 13 * 5 /bin/true\n")).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_systemd-crontab-generator")).arg("--explain").arg(&path).output().unwrap();
+    remove_file(&path).unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let expected = format!("{}:2: 30 2 13 * 5 /bin/true
+  minute       30           -> 30
+  hour         2            -> 02
+  day of month 13           -> 13
+  month        *            -> *
+  day of week  5            -> Fri
+  note: cron runs a job when either its day of month or its day of week matches,
+        the timer only when both do
+  DELAY=5
+", path.display());
+    assert!(stdout.starts_with(&expected), "{}", stdout);
+    assert!(stdout.contains("\n  OnCalendar=Fri *-*-13 02:30:00\n"), "{}", stdout);
+    assert_eq!(output.status.code(), Some(0));
+}
+
+#[test]
+fn validate_points_at_the_first_error() {
+    let path = env::temp_dir().join(format!("cron-validate-test-{}", std::process::id()));
+    File::create(&path).and_then(|mut file| file.write_all(b"0 5 * * * /bin/true\n  0 5 x? * * /bin/true\n@hourly\n")).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_systemd-crontab-generator")).arg("--validate").arg(&path).output().unwrap();
+    remove_file(&path).unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with(&format!("{}:2:7: skipping the line due to parsing error", path.display())), "{}", stdout);
+    assert_eq!(stdout.lines().count(), 1, "{}", stdout);
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn the_report_lists_the_jobs_of_every_crontab_soonest_first() {
+    let root = env::temp_dir().join(format!("cron-report-test-{}", std::process::id()));
+    create_dir_all(root.join("etc/cron.d")).unwrap();
+    File::create(root.join("etc/passwd")).and_then(|mut file| file.write_all(b"root:x:0:0:root:/root:/bin/sh\n")).unwrap();
+    File::create(root.join("etc/crontab")).and_then(|mut file| file.write_all(b"0 0 1 1 * root /bin/yearly\n")).unwrap();
+    File::create(root.join("etc/cron.d/backup"))
+        .and_then(|mut file| file.write_all(b"* * * * * root /bin/minutely\n@reboot root /bin/boot\n"))
+        .unwrap();
+    File::create(root.join("etc/anacrontab")).and_then(|mut file| file.write_all(b"7 10 weekly /bin/weekly\n")).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_systemd-crontab-generator")).arg("--report").arg("--root").arg(&root).output().unwrap();
+    remove_dir_all(&root).unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let position = |job: &str| stdout.find(job).unwrap_or_else(|| panic!("{} missing from {}", job, stdout));
+    assert!(position("/bin/minutely") < position("/bin/weekly"), "{}", stdout);
+    assert!(position("/bin/minutely") < position("/bin/yearly"), "{}", stdout);
+    assert!(position("/bin/weekly") < position("/bin/boot"), "{}", stdout);
+    assert!(position("/bin/yearly") < position("/bin/boot"), "{}", stdout);
+    assert!(stdout.contains("etc/cron.d/backup:2"), "{}", stdout);
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+}
@@ -0,0 +1,130 @@
+extern crate cronparse;
+extern crate systemd_crontab_generator;
+
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command, Stdio};
+
+use cronparse::crontab::SystemCrontabEntry;
+use systemd_crontab_generator::PACKAGE;
+use systemd_crontab_generator::process::{process_crontab_file, GeneratorConfig};
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("systemd-crontab-generator-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &Path, content: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    File::create(path).and_then(|mut file| file.write_all(content.as_bytes())).unwrap();
+}
+
+/// Runs `--install` below `root` with a systemctl that only logs its arguments.
+fn install(root: &Path, crontab: &Path, answer: &str) -> (i32, String) {
+    // the jobs run as users of the system below the root
+    write(&root.join("etc/passwd"), "root:x:0:0:root:/root:/bin/sh\n");
+    let bin = root.join("mock-bin");
+    write(&bin.join("systemctl"), &format!("#!/bin/sh\necho \"$@\" >> {}/systemctl.log\n", root.display()));
+    fs::set_permissions(bin.join("systemctl"), fs::Permissions::from_mode(0o755)).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_systemd-crontab-generator"))
+                        .arg("--install")
+                        .arg(crontab)
+                        .arg("--root")
+                        .arg(root)
+                        .env("PATH", format!("{}:{}", bin.display(), env::var("PATH").unwrap_or_default()))
+                        .stdin(Stdio::piped())
+                        .stdout(Stdio::null())
+                        .spawn()
+                        .unwrap();
+    child.stdin.take().unwrap().write_all(answer.as_bytes()).unwrap();
+    let status = child.wait().unwrap().code().unwrap();
+    (status, fs::read_to_string(root.join("systemctl.log")).unwrap_or_default())
+}
+
+fn installed_units(root: &Path) -> Vec<String> {
+    let mut units = fs::read_dir(root.join("etc/systemd/system"))
+                        .map(|dir| dir.map(|entry| entry.unwrap().file_name().into_string().unwrap()).collect::<Vec<_>>())
+                        .unwrap_or_default();
+    units.sort();
+    units
+}
+
+#[test]
+fn install_writes_units_and_enables_cron_target() {
+    let root = scratch_dir("install");
+    let crontab = root.join("etc/cron.d/jobs");
+    write(&crontab, "0 5 * * * root /bin/true\n@reboot root /bin/true\n");
+
+    let (status, systemctl) = install(&root, &crontab, "y\n");
+    assert_eq!(status, 0);
+    assert_eq!(systemctl, format!("--root={} enable cron.target\n", root.display()));
+    // a service and a timer for each job, @reboot ones included
+    let units = installed_units(&root);
+    assert_eq!(units.iter().filter(|unit| unit.ends_with(".service")).count(), 2, "{:?}", units);
+    assert_eq!(units.iter().filter(|unit| unit.ends_with(".timer")).count(), 2, "{:?}", units);
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn install_needs_confirmation() {
+    let root = scratch_dir("install-refused");
+    let crontab = root.join("etc/cron.d/jobs");
+    write(&crontab, "0 5 * * * root /bin/true\n");
+
+    let (status, systemctl) = install(&root, &crontab, "n\n");
+    assert_eq!(status, 1);
+    assert_eq!(systemctl, "");
+    assert!(installed_units(&root).is_empty());
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn generator_keeps_the_names_of_installed_jobs() {
+    let root = scratch_dir("install-generator");
+    let crontab = root.join("etc/cron.d/jobs");
+    write(&crontab, "0 5 * * * root /bin/true\n");
+    assert_eq!(install(&root, &crontab, "y\n").0, 0);
+
+    // the generator's units have the names of the installed ones, which hide them,
+    // rather than being renamed and running the job a second time
+    let config = GeneratorConfig::new(root.join("run/systemd/generator")).root(&root).dry_run(true);
+    let summary = process_crontab_file::<SystemCrontabEntry, _>(&crontab, &config);
+    assert!(summary.warnings.is_empty(), "{:?}", summary.warnings.iter().map(|w| w.to_string()).collect::<Vec<_>>());
+    let units = installed_units(&root);
+    for (name, _) in summary.jobs[0].units.iter().filter(|(name, _)| !name.ends_with(".sh")) {
+        assert!(units.contains(name), "{} not in {:?}", name, units);
+    }
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn install_twice_replaces_the_installed_units() {
+    let root = scratch_dir("install-twice");
+    let crontab = root.join("etc/cron.d/jobs");
+    write(&crontab, "0 5 * * * root /bin/true\nNAME=hello\n0 6 * * * root echo hello > /dev/null\n");
+
+    assert_eq!(install(&root, &crontab, "y\n").0, 0);
+    let units = installed_units(&root);
+    let (status, systemctl) = install(&root, &crontab, "y\n");
+    assert_eq!(status, 0);
+    assert_eq!(systemctl, format!("--root={} enable cron.target\n", root.display()).repeat(2));
+    assert_eq!(installed_units(&root), units);
+    assert!(units.contains(&"hello.service".to_owned()), "{:?}", units);
+
+    // the unit directory only gets units, the scripts of the commands, /bin/true
+    // included as there's none below the root, go with the package's state
+    assert!(!units.iter().any(|unit| unit.ends_with(".sh")), "{:?}", units);
+    let scripts = fs::read_dir(root.join("var/lib").join(PACKAGE)).unwrap().count();
+    assert_eq!(scripts, 2);
+
+    fs::remove_dir_all(&root).unwrap();
+}
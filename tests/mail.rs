@@ -0,0 +1,74 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("systemd-crontab-generator-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_script(path: &Path, content: &str) {
+    File::create(path).and_then(|mut file| file.write_all(content.as_bytes())).unwrap();
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+}
+
+/// Runs mail-on-failure for a unit whose `Environment=` systemctl shows as
+/// `environment`, with a mailer in `dir` that logs what it is given.
+fn mail_on_failure(dir: &Path, environment: &str) -> String {
+    write_script(&dir.join("systemctl"),
+                 &format!("#!/bin/sh\ncase \"$1 $4\" in\n\
+                           \"show User\") echo User= ;;\n\
+                           \"show Environment\") cat <<'EOF'\nEnvironment={}\nEOF\n;;\n\
+                           status*) echo the job failed ;;\n\
+                           esac\n",
+                          environment));
+    write_script(&dir.join("mock-mail"),
+                 &format!("#!/bin/sh\nprintf '%s|' \"$@\" > {0}/mail.log\necho >> {0}/mail.log\ncat >> {0}/mail.log\n",
+                          dir.display()));
+
+    let status = Command::new(env!("CARGO_BIN_EXE_mail-on-failure"))
+                     .arg("cron-job.service")
+                     .env("PATH", format!("{}:{}", dir.display(), env::var("PATH").unwrap_or_default()))
+                     .status()
+                     .unwrap();
+    assert!(status.success());
+    fs::read_to_string(dir.join("mail.log")).unwrap_or_default()
+}
+
+#[test]
+fn mail_command_takes_arguments() {
+    let dir = scratch_dir("mail-command");
+    let log = mail_on_failure(&dir,
+                              &format!(r#"MAILTO=alice,bob "MAIL_COMMAND={}/mock-mail -a \"my account\"" "A=b c""#,
+                                       dir.display()));
+    let mut lines = log.lines();
+    assert_eq!(lines.next(), Some("-a|my account|-i|alice|bob|"));
+    assert!(log.contains("\nTo: alice, bob\n"), "{}", log);
+    assert!(log.ends_with("\nthe job failed\n"), "{}", log);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn mail_is_sent_with_mail_by_default() {
+    if Path::new("/usr/bin/mail").exists() {
+        // the mail would really be sent
+        return;
+    }
+    let dir = scratch_dir("mail-default");
+    write_script(&dir.join("systemctl"), "#!/bin/sh\ncase \"$1 $4\" in\n\"show User\") echo User= ;;\nesac\n");
+    let output = Command::new(env!("CARGO_BIN_EXE_mail-on-failure"))
+                     .arg("cron-job.service")
+                     .env("PATH", format!("{}:{}", dir.display(), env::var("PATH").unwrap_or_default()))
+                     .output()
+                     .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout),
+               "<5>can't send mail for cron-job.service, /usr/bin/mail isn't installed\n");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,74 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("systemd-crontab-generator-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).unwrap();
+    for entry in fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_type().unwrap().is_dir() {
+            copy_dir(&entry.path(), &to.join(entry.file_name()));
+        } else {
+            fs::copy(entry.path(), to.join(entry.file_name())).unwrap();
+        }
+    }
+}
+
+/// The generated units with the given line, by name.
+fn units_with(dir: &Path, line: &str) -> Vec<(String, String)> {
+    let mut units = fs::read_dir(dir).unwrap()
+                        .map(|entry| entry.unwrap())
+                        .filter(|entry| entry.file_type().unwrap().is_file())
+                        .map(|entry| (entry.file_name().into_string().unwrap(), fs::read_to_string(entry.path()).unwrap()))
+                        .filter(|(_, unit)| unit.lines().any(|l| l == line))
+                        .collect::<Vec<_>>();
+    units.sort();
+    units
+}
+
+#[test]
+fn units_generated_below_a_root_refer_to_the_system_there() {
+    // a copy, as the units are written below the root as well
+    let scratch = scratch_dir("root");
+    copy_dir(&Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/sysroot"), &scratch.join("fixtures/sysroot"));
+
+    let status = Command::new(env!("CARGO_BIN_EXE_systemd-crontab-generator"))
+                     .current_dir(&scratch)
+                     .args(["--root", "./fixtures/sysroot", "/run/systemd/generator"])
+                     .status()
+                     .unwrap();
+    assert!(status.success());
+    let dstdir = scratch.join("fixtures/sysroot/run/systemd/generator");
+
+    // alice is only in the passwd file below the root
+    let services = units_with(&dstdir, "SourcePath=/etc/cron.d/backup");
+    let (_, service) = services.iter().find(|(name, _)| name.ends_with(".service")).expect("no service for alice's job");
+    assert!(service.lines().any(|l| l == "User=alice"), "{}", service);
+    let exec_start = service.lines().find(|l| l.starts_with("ExecStart=")).unwrap();
+    assert!(exec_start.starts_with("ExecStart=/run/systemd/generator/cron-") && exec_start.ends_with(".sh"), "{}", exec_start);
+    let (timer_name, timer) = services.iter().find(|(name, _)| name.ends_with(".timer")).unwrap();
+    assert!(timer.starts_with(&format!("# Generated by systemd-crontab-generator {}\n# /etc/cron.d/backup:2\n",
+                                       env!("CARGO_PKG_VERSION"))),
+            "{}",
+            timer);
+    assert_eq!(fs::read_link(dstdir.join("cron.target.wants").join(timer_name)).unwrap(),
+               Path::new("/run/systemd/generator").join(timer_name));
+
+    // so is the user named by the crontab in the spool directory
+    let user_units = units_with(&dstdir, "SourcePath=/var/spool/cron/crontabs/alice");
+    assert_eq!(user_units.len(), 2, "{:?}", user_units);
+
+    for (name, unit) in units_with(&dstdir, "Documentation=man:systemd-crontab-generator(8)") {
+        assert!(!unit.contains("fixtures"), "{}: {}", name, unit);
+    }
+
+    fs::remove_dir_all(&scratch).unwrap();
+}
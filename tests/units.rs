@@ -0,0 +1,18 @@
+use std::fs::File;
+use std::io::Read;
+
+fn built_unit(name: &str) -> String {
+    let mut unit = String::new();
+    File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/out/build/units/").to_owned() + name)
+        .and_then(|mut file| file.read_to_string(&mut unit))
+        .unwrap();
+    unit
+}
+
+#[test]
+fn run_parts_directories_share_one_template_service() {
+    let service = built_unit("cron-run-parts@.service");
+    assert!(service.contains("PartOf=cron-%i.target\n"), "{}", service);
+    assert!(service.lines().any(|line| line.starts_with("ExecStart=") && line.ends_with("/etc/cron.%i")), "{}", service);
+    assert!(File::open(concat!(env!("CARGO_MANIFEST_DIR"), "/units/cron-schedule.service.in")).is_err());
+}